@@ -1,23 +1,29 @@
 use bevy::{prelude::*, window::PresentMode};
+use bevy_demos::{
+    characters::{
+        CharacterSelectPlugin, CharacterSheet, DemoState, MovementStats, SelectedCharacter,
+    },
+    timeline::{Timeline, TimelinePlugin, WaveMember, Waves},
+};
 use std::convert::From;
 
 const TITLE: &str = "bv08 Simple BG Scroll";
 const WIN_W: f32 = 1280.;
 const WIN_H: f32 = 720.;
 
-const PLAYER_SPEED: f32 = 500.;
-const ACCEL_RATE: f32 = 5000.;
-
 const TILE_SIZE: u32 = 100;
 
 const SCROLL_SPEED: f32 = 120.;
 
-enum PlayerType {
-    Bird,
-    Plane,
-    UFO,
-    Helicopter,
-}
+/// When and where enemies come in
+const TIMELINE: &str = "waves/scroll.timeline";
+/// The only enemy type this example has
+const ENEMY: &str = "drone";
+const ENEMY_RADIUS: f32 = 32.;
+/// How close the middle of the player has to get to bump an enemy
+const PLAYER_RADIUS: f32 = 30.;
+/// How fast bumping an enemy knocks the player away
+const KNOCKBACK_SPEED: f32 = 600.;
 
 #[derive(Component)]
 struct Player;
@@ -25,6 +31,10 @@ struct Player;
 #[derive(Component)]
 struct Background;
 
+/// How far the background has scrolled while playing
+#[derive(Resource, Default, Deref, DerefMut)]
+struct Scrolled(f32);
+
 #[derive(Component, Deref, DerefMut)]
 struct Velocity {
     velocity: Vec2,
@@ -56,18 +66,23 @@ fn main() {
             }),
             ..default()
         }))
+        .add_plugins((CharacterSelectPlugin, TimelinePlugin))
+        .init_resource::<Scrolled>()
         .add_systems(Startup, setup)
-        .add_systems(Update, move_player)
+        .add_systems(OnEnter(DemoState::Playing), spawn_player)
+        .add_systems(
+            Update,
+            (move_player, spawn_waves, move_enemies, bump_enemies)
+                .chain()
+                .run_if(in_state(DemoState::Playing)),
+        )
         .add_systems(Update, scroll_bg)
         .run();
 }
 
-fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
-) {
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2d);
+    commands.insert_resource(Waves::load(&asset_server, TIMELINE, vec![ENEMY]));
 
     for start_x in [0., WIN_W] {
         commands.spawn((
@@ -77,23 +92,21 @@ fn setup(
             Background,
         ));
     }
+}
 
-    let bird_sheet_handle = asset_server.load("birds.png");
-    let bird_layout = TextureAtlasLayout::from_grid(UVec2::splat(TILE_SIZE), 2, 2, None, None);
-    let bird_layout_handle = texture_atlases.add(bird_layout);
+fn spawn_player(
+    mut commands: Commands,
+    sheet: Res<CharacterSheet>,
+    selected: Res<SelectedCharacter>,
+) {
     commands.spawn((
-        Sprite::from_atlas_image(
-            bird_sheet_handle,
-            TextureAtlas {
-                layout: bird_layout_handle,
-                index: PlayerType::Plane as usize,
-            },
-        ),
+        sheet.sprite(**selected),
         Transform {
             translation: Vec3::new(0., 0., 900.),
             ..default()
         },
         Velocity::new(),
+        selected.stats(),
         Player,
     ));
 }
@@ -114,9 +127,12 @@ fn scroll_bg(
 fn move_player(
     time: Res<Time>,
     input: Res<ButtonInput<KeyCode>>,
-    player: Single<(&mut Transform, &mut Velocity), (With<Player>, Without<Background>)>,
+    player: Single<
+        (&mut Transform, &mut Velocity, &MovementStats),
+        (With<Player>, Without<Background>),
+    >,
 ) {
-    let (mut transform, mut velocity) = player.into_inner();
+    let (mut transform, mut velocity, stats) = player.into_inner();
 
     let mut dir = Vec2::ZERO;
 
@@ -137,23 +153,89 @@ fn move_player(
     }
 
     let deltat = time.delta_secs();
-    let accel = ACCEL_RATE * deltat;
-
-    **velocity = if dir.length() > 0. {
-        (**velocity + (dir.normalize_or_zero() * accel)).clamp_length_max(PLAYER_SPEED)
-    } else if velocity.length() > accel {
-        **velocity + (velocity.normalize_or_zero() * -accel)
-    } else {
-        Vec2::ZERO
-    };
+    let flap = input.just_pressed(KeyCode::KeyW);
+
+    **velocity = stats.step(**velocity, dir, flap, deltat);
     let change = **velocity * deltat;
 
     let max = Vec2::new(
         WIN_W / 2. - (TILE_SIZE as f32) / 2.,
         WIN_H / 2. - (TILE_SIZE as f32) / 2.,
     );
-    let min = max.clone() * -1.;
+    let min = max * -1.;
+
+    let moved = transform.translation + change.extend(0.);
+    transform.translation = moved.clamp(min.extend(900.), max.extend(900.));
+
+    // Stop against the edges, so a falling bird doesn't build up speed there
+    if transform.translation.x != moved.x {
+        velocity.x = 0.;
+    }
+    if transform.translation.y != moved.y {
+        velocity.y = 0.;
+    }
+}
+
+fn spawn_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut scrolled: ResMut<Scrolled>,
+    mut waves: ResMut<Waves>,
+    timelines: Res<Assets<Timeline>>,
+    asset_server: Res<AssetServer>,
+    enemies: Query<(), With<WaveMember>>,
+) {
+    **scrolled += SCROLL_SPEED * time.delta_secs();
 
-    transform.translation =
-        (transform.translation + change.extend(0.)).clamp(min.extend(900.), max.extend(900.));
+    for spawn in waves.advance(&timelines, time.delta_secs(), **scrolled) {
+        if spawn.enemy != ENEMY {
+            // Already reported when the timeline loaded
+            continue;
+        }
+        for member in WaveMember::members(&spawn, ENEMY_RADIUS) {
+            let mut sprite = Sprite::from_image(asset_server.load("red_circle.png"));
+            sprite.custom_size = Some(Vec2::splat(ENEMY_RADIUS * 2.));
+            commands.spawn((
+                sprite,
+                Transform::from_translation(member.position(WIN_W).extend(500.)),
+                member,
+            ));
+        }
+    }
+
+    // Start the timeline again once the last wave is through
+    if waves.finished(&timelines) && enemies.is_empty() {
+        waves.restart(**scrolled);
+    }
+}
+
+fn move_enemies(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut enemies: Query<(Entity, &mut Transform, &mut WaveMember)>,
+) {
+    for (entity, mut transform, mut member) in enemies.iter_mut() {
+        member.age += time.delta_secs();
+        transform.translation = member.position(WIN_W).extend(transform.translation.z);
+
+        if member.gone(WIN_W) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Bumping into an enemy pops it and knocks the player away
+fn bump_enemies(
+    mut commands: Commands,
+    player: Single<(&Transform, &mut Velocity), With<Player>>,
+    enemies: Query<(Entity, &Transform, &WaveMember), Without<Player>>,
+) {
+    let (transform, mut velocity) = player.into_inner();
+    for (entity, enemy_transform, member) in enemies.iter() {
+        let away = transform.translation.truncate() - enemy_transform.translation.truncate();
+        if away.length() < PLAYER_RADIUS + member.radius {
+            commands.entity(entity).despawn();
+            **velocity = away.normalize_or(Vec2::NEG_X) * KNOCKBACK_SPEED;
+        }
+    }
 }
@@ -1,23 +1,29 @@
 use bevy::{prelude::*, window::PresentMode};
+use bevy_demos::{
+    characters::{
+        CharacterSelectPlugin, CharacterSheet, DemoState, MovementStats, SelectedCharacter,
+    },
+    timeline::{Timeline, TimelinePlugin, WaveMember, Waves},
+};
 use std::convert::From;
 
 const TITLE: &str = "bv09 Side Scroll";
 const WIN_W: f32 = 1280.;
 const WIN_H: f32 = 720.;
 
-const PLAYER_SPEED: f32 = 500.;
-const ACCEL_RATE: f32 = 5000.;
-
 const TILE_SIZE: u32 = 100;
 
 const LEVEL_LEN: f32 = 5000.;
 
-enum PlayerType {
-    Bird,
-    Plane,
-    UFO,
-    Helicopter,
-}
+/// When and where enemies come in, by how far the camera has moved
+const TIMELINE: &str = "waves/side_scroll.timeline";
+/// The only enemy type this example has
+const ENEMY: &str = "drone";
+const ENEMY_RADIUS: f32 = 32.;
+/// How close the middle of the player has to get to bump an enemy
+const PLAYER_RADIUS: f32 = 30.;
+/// How fast bumping an enemy knocks the player away
+const KNOCKBACK_SPEED: f32 = 600.;
 
 #[derive(Component)]
 struct Player;
@@ -59,9 +65,21 @@ fn main() {
             }),
             ..default()
         }))
+        .add_plugins((CharacterSelectPlugin, TimelinePlugin))
         .add_systems(Startup, setup)
-        .add_systems(Update, move_player)
-        .add_systems(Update, move_camera.after(move_player))
+        .add_systems(OnEnter(DemoState::Playing), spawn_player)
+        .add_systems(
+            Update,
+            (
+                move_player,
+                move_camera,
+                spawn_waves,
+                move_enemies,
+                bump_enemies,
+            )
+                .chain()
+                .run_if(in_state(DemoState::Playing)),
+        )
         .run();
 }
 
@@ -71,6 +89,7 @@ fn setup(
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
 ) {
     commands.spawn(Camera2d);
+    commands.insert_resource(Waves::load(&asset_server, TIMELINE, vec![ENEMY]));
 
     let bg_texture_handle = asset_server.load("small_bg.png");
 
@@ -85,25 +104,6 @@ fn setup(
         x_offset += WIN_W;
     }
 
-    let bird_sheet_handle = asset_server.load("birds.png");
-    let bird_layout = TextureAtlasLayout::from_grid(UVec2::splat(TILE_SIZE), 2, 2, None, None);
-    let bird_layout_handle = texture_atlases.add(bird_layout);
-    commands.spawn((
-        Sprite::from_atlas_image(
-            bird_sheet_handle,
-            TextureAtlas {
-                layout: bird_layout_handle,
-                index: PlayerType::Helicopter as usize,
-            },
-        ),
-        Transform {
-            translation: Vec3::new(0., 0., 900.),
-            ..default()
-        },
-        Velocity::new(),
-        Player,
-    ));
-
     let brick_sheet_handle = asset_server.load("bricks.png");
     let brick_layout = TextureAtlasLayout::from_grid(UVec2::splat(TILE_SIZE), 4, 1, None, None);
     let brick_layout_len = brick_layout.len();
@@ -137,12 +137,32 @@ fn setup(
     }
 }
 
+fn spawn_player(
+    mut commands: Commands,
+    sheet: Res<CharacterSheet>,
+    selected: Res<SelectedCharacter>,
+) {
+    commands.spawn((
+        sheet.sprite(**selected),
+        Transform {
+            translation: Vec3::new(0., 0., 900.),
+            ..default()
+        },
+        Velocity::new(),
+        selected.stats(),
+        Player,
+    ));
+}
+
 fn move_player(
     time: Res<Time>,
     input: Res<ButtonInput<KeyCode>>,
-    player: Single<(&mut Transform, &mut Velocity), (With<Player>, Without<Background>)>,
+    player: Single<
+        (&mut Transform, &mut Velocity, &MovementStats),
+        (With<Player>, Without<Background>),
+    >,
 ) {
-    let (mut transform, mut velocity) = player.into_inner();
+    let (mut transform, mut velocity, stats) = player.into_inner();
 
     let mut dir = Vec2::ZERO;
 
@@ -163,15 +183,9 @@ fn move_player(
     }
 
     let deltat = time.delta_secs();
-    let accel = ACCEL_RATE * deltat;
-
-    **velocity = if dir.length() > 0. {
-        (**velocity + (dir.normalize_or_zero() * accel)).clamp_length_max(PLAYER_SPEED)
-    } else if velocity.length() > accel {
-        **velocity + (velocity.normalize_or_zero() * -accel)
-    } else {
-        Vec2::ZERO
-    };
+    let flap = input.just_pressed(KeyCode::KeyW);
+
+    **velocity = stats.step(**velocity, dir, flap, deltat);
     let change = **velocity * deltat;
 
     let min = Vec3::new(
@@ -185,7 +199,16 @@ fn move_player(
         900.,
     );
 
-    transform.translation = (transform.translation + change.extend(0.)).clamp(min, max);
+    let moved = transform.translation + change.extend(0.);
+    transform.translation = moved.clamp(min, max);
+
+    // Stop against the edges, so a falling bird doesn't build up speed there
+    if transform.translation.x != moved.x {
+        velocity.x = 0.;
+    }
+    if transform.translation.y != moved.y {
+        velocity.y = 0.;
+    }
 }
 
 fn move_camera(
@@ -194,3 +217,64 @@ fn move_camera(
 ) {
     camera.translation.x = player.translation.x.clamp(0., LEVEL_LEN - WIN_W);
 }
+
+/// Enemies come in from the right of the view, so they follow the camera
+fn spawn_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut waves: ResMut<Waves>,
+    timelines: Res<Assets<Timeline>>,
+    asset_server: Res<AssetServer>,
+    camera: Single<&Transform, With<Camera>>,
+) {
+    let scrolled = camera.translation.x;
+    for spawn in waves.advance(&timelines, time.delta_secs(), scrolled) {
+        if spawn.enemy != ENEMY {
+            // Already reported when the timeline loaded
+            continue;
+        }
+        for member in WaveMember::members(&spawn, ENEMY_RADIUS) {
+            let mut sprite = Sprite::from_image(asset_server.load("red_circle.png"));
+            sprite.custom_size = Some(Vec2::splat(ENEMY_RADIUS * 2.));
+            let position = member.position(WIN_W) + Vec2::new(scrolled, 0.);
+            commands.spawn((
+                sprite,
+                Transform::from_translation(position.extend(500.)),
+                member,
+            ));
+        }
+    }
+}
+
+fn move_enemies(
+    mut commands: Commands,
+    time: Res<Time>,
+    camera: Single<&Transform, With<Camera>>,
+    mut enemies: Query<(Entity, &mut Transform, &mut WaveMember), Without<Camera>>,
+) {
+    for (entity, mut transform, mut member) in enemies.iter_mut() {
+        member.age += time.delta_secs();
+        let position = member.position(WIN_W) + Vec2::new(camera.translation.x, 0.);
+        transform.translation = position.extend(transform.translation.z);
+
+        if member.gone(WIN_W) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Bumping into an enemy pops it and knocks the player away
+fn bump_enemies(
+    mut commands: Commands,
+    player: Single<(&Transform, &mut Velocity), With<Player>>,
+    enemies: Query<(Entity, &Transform, &WaveMember), Without<Player>>,
+) {
+    let (transform, mut velocity) = player.into_inner();
+    for (entity, enemy_transform, member) in enemies.iter() {
+        let away = transform.translation.truncate() - enemy_transform.translation.truncate();
+        if away.length() < PLAYER_RADIUS + member.radius {
+            commands.entity(entity).despawn();
+            **velocity = away.normalize_or(Vec2::NEG_X) * KNOCKBACK_SPEED;
+        }
+    }
+}
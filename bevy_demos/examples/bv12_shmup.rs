@@ -0,0 +1,494 @@
+// Bevy queries routinely trip this lint
+#![allow(clippy::type_complexity)]
+
+use bevy::{prelude::*, window::PresentMode};
+use bevy_demos::{
+    characters::{
+        CharacterSelectPlugin, CharacterSheet, DemoState, MovementStats, SelectedCharacter,
+    },
+    pool::{Pool, Pooled},
+    timeline::{Timeline, TimelinePlugin, WaveMember, Waves},
+};
+use std::convert::From;
+
+const TITLE: &str = "bv12 Shmup";
+const WIN_W: f32 = 1280.;
+const WIN_H: f32 = 720.;
+
+const TILE_SIZE: u32 = 100;
+
+const SCROLL_SPEED: f32 = 120.;
+
+const SHOT_SPEED: f32 = 1100.;
+/// Seconds between shots while fire is held
+const FIRE_COOLDOWN: f32 = 0.15;
+/// Most shots on screen at once
+const SHOT_POOL_SIZE: usize = 32;
+const SHOT_RADIUS: f32 = 8.;
+
+/// How close the middle of the player has to get to be hit
+const PLAYER_RADIUS: f32 = 30.;
+
+const STARTING_LIVES: u32 = 3;
+/// Seconds the player blinks and can't be hit after losing a life
+const INVULNERABLE_TIME: f32 = 1.5;
+
+/// When and where enemies come in
+const TIMELINE: &str = "waves/shmup.timeline";
+
+/// What each enemy type in the timeline looks like and takes to bring down
+struct EnemyKind {
+    name: &'static str,
+    radius: f32,
+    health: u32,
+    points: u32,
+    color: Color,
+}
+
+const ENEMY_KINDS: [EnemyKind; 2] = [
+    EnemyKind {
+        name: "drone",
+        radius: 32.,
+        health: 1,
+        points: 100,
+        color: Color::WHITE,
+    },
+    EnemyKind {
+        name: "heavy",
+        radius: 48.,
+        health: 4,
+        points: 400,
+        color: Color::srgb(0.6, 0.3, 0.3),
+    },
+];
+
+fn enemy_kind(name: &str) -> Option<&'static EnemyKind> {
+    ENEMY_KINDS.iter().find(|kind| kind.name == name)
+}
+
+#[derive(Component)]
+struct Player;
+
+#[derive(Component)]
+struct Background;
+
+#[derive(Component)]
+struct Shot;
+
+#[derive(Component)]
+struct Enemy {
+    health: u32,
+    points: u32,
+}
+
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component, Deref, DerefMut)]
+struct Velocity {
+    velocity: Vec2,
+}
+
+impl Velocity {
+    fn new() -> Self {
+        Self {
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+impl From<Vec2> for Velocity {
+    fn from(velocity: Vec2) -> Self {
+        Self { velocity }
+    }
+}
+
+#[derive(Resource)]
+struct Game {
+    score: u32,
+    lives: u32,
+    /// How far the background has scrolled
+    scrolled: f32,
+    fire_cooldown: Timer,
+    invulnerable: Timer,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self {
+            score: 0,
+            lives: STARTING_LIVES,
+            scrolled: 0.,
+            fire_cooldown: Timer::from_seconds(FIRE_COOLDOWN, TimerMode::Once),
+            invulnerable: Timer::from_seconds(0., TimerMode::Once),
+        }
+    }
+}
+
+impl Game {
+    fn over(&self) -> bool {
+        self.lives == 0
+    }
+}
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::Srgba(Srgba::gray(0.25))))
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: TITLE.into(),
+                resolution: (WIN_W, WIN_H).into(),
+                present_mode: PresentMode::AutoVsync,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((CharacterSelectPlugin, TimelinePlugin))
+        .init_resource::<Game>()
+        .add_systems(Startup, setup)
+        .add_systems(OnEnter(DemoState::Playing), spawn_player)
+        .add_systems(Update, scroll_bg)
+        .add_systems(
+            Update,
+            (
+                move_player,
+                fire,
+                move_shots,
+                spawn_waves,
+                move_enemies,
+                shoot_enemies,
+                hit_player,
+                restart,
+                update_hud,
+            )
+                .chain()
+                .run_if(in_state(DemoState::Playing)),
+        )
+        .run();
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+    let known = ENEMY_KINDS.iter().map(|kind| kind.name).collect();
+    commands.insert_resource(Waves::load(&asset_server, TIMELINE, known));
+
+    for start_x in [0., WIN_W] {
+        commands.spawn((
+            Sprite::from_image(asset_server.load("small_bg.png")),
+            Transform::from_xyz(start_x, 0., -1.),
+            Velocity::from(Vec2::new(SCROLL_SPEED, 0.)),
+            Background,
+        ));
+    }
+
+    // Every shot that will ever be fired, waiting off to the side
+    let shot_image = asset_server.load("blue_circle.png");
+    let pool = Pool::<Shot>::spawn(&mut commands, SHOT_POOL_SIZE, || {
+        let mut sprite = Sprite::from_image(shot_image.clone());
+        sprite.custom_size = Some(Vec2::splat(SHOT_RADIUS * 2.));
+        (sprite, Transform::from_xyz(0., 0., 800.), Shot)
+    });
+    commands.insert_resource(pool);
+
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 24.,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        },
+        Hud,
+    ));
+}
+
+fn spawn_player(
+    mut commands: Commands,
+    sheet: Res<CharacterSheet>,
+    selected: Res<SelectedCharacter>,
+) {
+    commands.spawn((
+        sheet.sprite(**selected),
+        Transform {
+            translation: Vec3::new(-WIN_W / 4., 0., 900.),
+            ..default()
+        },
+        Velocity::new(),
+        selected.stats(),
+        Player,
+    ));
+}
+
+fn scroll_bg(
+    time: Res<Time>,
+    mut bg: Query<(&mut Transform, &Velocity), (With<Background>, Without<Player>)>,
+) {
+    let deltat = time.delta_secs();
+    for (mut bt, bv) in bg.iter_mut() {
+        bt.translation -= bv.velocity.extend(0.) * deltat;
+        if bt.translation.x < -WIN_W {
+            bt.translation += Vec3::new(WIN_W * 2., 0., 0.);
+        }
+    }
+}
+
+fn move_player(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    game: Res<Game>,
+    player: Single<
+        (&mut Transform, &mut Velocity, &MovementStats),
+        (With<Player>, Without<Background>),
+    >,
+) {
+    if game.over() {
+        return;
+    }
+    let (mut transform, mut velocity, stats) = player.into_inner();
+
+    let mut dir = Vec2::ZERO;
+
+    if input.pressed(KeyCode::KeyA) {
+        dir.x -= 1.;
+    }
+
+    if input.pressed(KeyCode::KeyD) {
+        dir.x += 1.;
+    }
+
+    if input.pressed(KeyCode::KeyW) {
+        dir.y += 1.;
+    }
+
+    if input.pressed(KeyCode::KeyS) {
+        dir.y -= 1.;
+    }
+
+    let deltat = time.delta_secs();
+    let flap = input.just_pressed(KeyCode::KeyW);
+
+    **velocity = stats.step(**velocity, dir, flap, deltat);
+    let change = **velocity * deltat;
+
+    let max = Vec2::new(
+        WIN_W / 2. - (TILE_SIZE as f32) / 2.,
+        WIN_H / 2. - (TILE_SIZE as f32) / 2.,
+    );
+    let min = max * -1.;
+
+    let moved = transform.translation + change.extend(0.);
+    transform.translation = moved.clamp(min.extend(900.), max.extend(900.));
+
+    if transform.translation.x != moved.x {
+        velocity.x = 0.;
+    }
+    if transform.translation.y != moved.y {
+        velocity.y = 0.;
+    }
+}
+
+/// Space fires, taking a shot from the pool rather than spawning one
+fn fire(
+    mut commands: Commands,
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut game: ResMut<Game>,
+    mut pool: ResMut<Pool<Shot>>,
+    player: Single<&Transform, With<Player>>,
+    mut shots: Query<&mut Transform, (With<Shot>, Without<Player>)>,
+) {
+    game.fire_cooldown.tick(time.delta());
+    if game.over() || !input.pressed(KeyCode::Space) || !game.fire_cooldown.finished() {
+        return;
+    }
+
+    // With every shot in the air, wait for one to come back
+    let Some(shot) = pool.take(&mut commands) else {
+        return;
+    };
+    if let Ok(mut transform) = shots.get_mut(shot) {
+        transform.translation = player.translation + Vec3::new(TILE_SIZE as f32 / 2., 0., -100.);
+    }
+    game.fire_cooldown.reset();
+}
+
+fn move_shots(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<Pool<Shot>>,
+    mut shots: Query<(Entity, &mut Transform), (With<Shot>, Without<Pooled>)>,
+) {
+    for (entity, mut transform) in shots.iter_mut() {
+        transform.translation.x += SHOT_SPEED * time.delta_secs();
+        if transform.translation.x > WIN_W / 2. + SHOT_RADIUS {
+            pool.release(&mut commands, entity);
+        }
+    }
+}
+
+fn spawn_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut game: ResMut<Game>,
+    mut waves: ResMut<Waves>,
+    timelines: Res<Assets<Timeline>>,
+    asset_server: Res<AssetServer>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    if game.over() {
+        return;
+    }
+    game.scrolled += SCROLL_SPEED * time.delta_secs();
+
+    for spawn in waves.advance(&timelines, time.delta_secs(), game.scrolled) {
+        let Some(kind) = enemy_kind(&spawn.enemy) else {
+            // Already reported when the timeline loaded
+            continue;
+        };
+
+        for member in WaveMember::members(&spawn, kind.radius) {
+            let mut sprite = Sprite::from_image(asset_server.load("red_circle.png"));
+            sprite.custom_size = Some(Vec2::splat(kind.radius * 2.));
+            sprite.color = kind.color;
+
+            commands.spawn((
+                sprite,
+                Transform::from_translation(member.position(WIN_W).extend(500.)),
+                member,
+                Enemy {
+                    health: kind.health,
+                    points: kind.points,
+                },
+            ));
+        }
+    }
+
+    // Start the timeline again once the last wave is through
+    if waves.finished(&timelines) && enemies.is_empty() {
+        waves.restart(game.scrolled);
+    }
+}
+
+fn move_enemies(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut enemies: Query<(Entity, &mut Transform, &mut WaveMember)>,
+) {
+    for (entity, mut transform, mut member) in enemies.iter_mut() {
+        member.age += time.delta_secs();
+        transform.translation = member.position(WIN_W).extend(transform.translation.z);
+
+        if member.gone(WIN_W) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn shoot_enemies(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    mut pool: ResMut<Pool<Shot>>,
+    shots: Query<(Entity, &Transform), (With<Shot>, Without<Pooled>)>,
+    mut enemies: Query<(Entity, &Transform, &WaveMember, &mut Enemy)>,
+) {
+    // Shots stay in the query until the commands run, so only use each once
+    let mut spent = Vec::new();
+    for (entity, enemy_transform, member, mut enemy) in enemies.iter_mut() {
+        let hit = shots.iter().find(|(shot, shot_transform)| {
+            !spent.contains(shot)
+                && shot_transform
+                    .translation
+                    .truncate()
+                    .distance(enemy_transform.translation.truncate())
+                    < SHOT_RADIUS + member.radius
+        });
+        if let Some((shot, _)) = hit {
+            pool.release(&mut commands, shot);
+            spent.push(shot);
+            enemy.health -= 1;
+            if enemy.health == 0 {
+                commands.entity(entity).despawn();
+                game.score += enemy.points;
+            }
+        }
+    }
+}
+
+fn hit_player(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut game: ResMut<Game>,
+    player: Single<(&Transform, &mut Visibility), With<Player>>,
+    enemies: Query<(Entity, &Transform, &WaveMember), Without<Player>>,
+) {
+    let (player_transform, mut visibility) = player.into_inner();
+    game.invulnerable.tick(time.delta());
+
+    // Blink while invulnerable, and stay hidden once out of lives
+    *visibility = if game.over()
+        || (!game.invulnerable.finished() && game.invulnerable.elapsed_secs() % 0.2 < 0.1)
+    {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if game.over() || !game.invulnerable.finished() {
+        return;
+    }
+
+    for (entity, enemy_transform, member) in enemies.iter() {
+        let distance = player_transform
+            .translation
+            .truncate()
+            .distance(enemy_transform.translation.truncate());
+        if distance < PLAYER_RADIUS + member.radius {
+            commands.entity(entity).despawn();
+            game.lives -= 1;
+            game.invulnerable = Timer::from_seconds(INVULNERABLE_TIME, TimerMode::Once);
+            info!("Hit! {} lives left", game.lives);
+            break;
+        }
+    }
+}
+
+/// After a game over, R starts again from the first wave
+fn restart(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut game: ResMut<Game>,
+    mut waves: ResMut<Waves>,
+    mut pool: ResMut<Pool<Shot>>,
+    enemies: Query<Entity, With<Enemy>>,
+    shots: Query<Entity, (With<Shot>, Without<Pooled>)>,
+) {
+    if !game.over() || !input.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+
+    for enemy in &enemies {
+        commands.entity(enemy).despawn();
+    }
+    for shot in &shots {
+        pool.release(&mut commands, shot);
+    }
+    *game = Game::default();
+    waves.restart(0.);
+}
+
+fn update_hud(game: Res<Game>, pool: Res<Pool<Shot>>, mut hud: Single<&mut Text, With<Hud>>) {
+    ***hud = if game.over() {
+        format!("GAME OVER  Score: {}  (R to play again)", game.score)
+    } else {
+        format!(
+            "Score: {}  Lives: {}  Shots: {}/{}",
+            game.score,
+            game.lives,
+            pool.free(),
+            SHOT_POOL_SIZE
+        )
+    };
+}
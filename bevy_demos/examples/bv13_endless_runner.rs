@@ -0,0 +1,603 @@
+// Bevy queries routinely trip this lint
+#![allow(clippy::type_complexity)]
+
+use bevy::{prelude::*, window::PresentMode};
+use bevy_demos::{
+    characters::{
+        CharacterSelectPlugin, CharacterSheet, DemoState, MovementStats, SelectedCharacter,
+    },
+    pool::{Pool, Pooled},
+    timeline::{Timeline, TimelinePlugin, WaveMember, Waves},
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+
+const TITLE: &str = "bv13 Endless Runner";
+const WIN_W: f32 = 1280.;
+const WIN_H: f32 = 720.;
+
+const TILE_SIZE: u32 = 100;
+
+/// World speed at the start of a run
+const START_SPEED: f32 = 250.;
+/// Speed gained every second
+const SPEED_RAMP: f32 = 8.;
+const MAX_SPEED: f32 = 900.;
+/// The background drifts slower than the obstacles, to look further away
+const PARALLAX: f32 = 0.3;
+
+/// Seed used unless one is given on the command line
+const DEFAULT_SEED: u64 = 1666;
+/// How far past the right edge of the screen chunks are built
+const LOOKAHEAD: f32 = WIN_W / 2.;
+/// Enough bricks for every chunk that can be on screen at once
+const BRICK_POOL_SIZE: usize = 128;
+/// How close the middle of the player can get to a brick's edge
+const PLAYER_RADIUS: f32 = 30.;
+
+/// When and where enemies come in, by how far the course has scrolled
+const TIMELINE: &str = "waves/runner.timeline";
+/// The only enemy type this example has
+const ENEMY: &str = "drone";
+const ENEMY_RADIUS: f32 = 32.;
+
+/// Pieces of course, stitched together at random
+///
+/// Each is seven rows of tiles read top to bottom, which fills the height of
+/// the window, with `#` for a brick and `.` for open air.
+#[rustfmt::skip]
+const TEMPLATES: [[&str; 7]; 8] = [
+    // Gaps and pillars
+    [
+        "......",
+        "......",
+        "......",
+        "...#..",
+        "...#..",
+        "...#..",
+        "...#..",
+    ],
+    [
+        "..#...",
+        "..#...",
+        "..#...",
+        "..#...",
+        "......",
+        "......",
+        "......",
+    ],
+    [
+        ".#...#",
+        ".#...#",
+        "......",
+        "......",
+        "......",
+        ".#...#",
+        ".#...#",
+    ],
+    // A tunnel to thread
+    [
+        "########",
+        "########",
+        "........",
+        "........",
+        "...##...",
+        "........",
+        "........",
+    ],
+    [
+        "........",
+        "........",
+        "...##...",
+        "........",
+        "........",
+        "########",
+        "########",
+    ],
+    // Floating blocks
+    [
+        ".....",
+        ".##..",
+        ".....",
+        "...##",
+        ".....",
+        ".##..",
+        ".....",
+    ],
+    // Stairs up and down
+    [
+        "......#",
+        ".....##",
+        "......#",
+        ".......",
+        "#......",
+        "##.....",
+        "###....",
+    ],
+    // Room to breathe
+    [
+        "....",
+        "....",
+        "....",
+        "....",
+        "....",
+        "....",
+        "....",
+    ],
+];
+
+#[derive(Component)]
+struct Player;
+
+#[derive(Component)]
+struct Background;
+
+#[derive(Component)]
+struct Brick;
+
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component, Deref, DerefMut)]
+struct Velocity {
+    velocity: Vec2,
+}
+
+impl Velocity {
+    fn new() -> Self {
+        Self {
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+/// A template placed in the world, and the bricks borrowed to build it
+struct Chunk {
+    right_edge: f32,
+    bricks: Vec<Entity>,
+}
+
+#[derive(Resource)]
+struct Run {
+    seed: u64,
+    /// ChaCha rather than StdRng, which can change between rand versions and
+    /// platforms, so a seed always gives the same course
+    rng: ChaCha8Rng,
+    speed: f32,
+    distance: f32,
+    best: f32,
+    /// Where the next chunk starts, in screen space
+    next_chunk_x: f32,
+    chunks: VecDeque<Chunk>,
+    over: bool,
+}
+
+impl Run {
+    fn new(seed: u64, best: f32) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            speed: START_SPEED,
+            distance: 0.,
+            best,
+            // Leave the first screen empty
+            next_chunk_x: WIN_W / 2.,
+            chunks: VecDeque::new(),
+            over: false,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct BrickSheet {
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+fn main() {
+    // cargo run --example bv13_endless_runner -- <seed>
+    let seed = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_SEED);
+
+    App::new()
+        .insert_resource(ClearColor(Color::Srgba(Srgba::gray(0.25))))
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: TITLE.into(),
+                resolution: (WIN_W, WIN_H).into(),
+                present_mode: PresentMode::AutoVsync,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((CharacterSelectPlugin, TimelinePlugin))
+        .insert_resource(Run::new(seed, 0.))
+        .add_systems(Startup, setup)
+        .add_systems(OnEnter(DemoState::Playing), spawn_player)
+        .add_systems(
+            Update,
+            (
+                speed_up,
+                scroll_bg,
+                build_chunks,
+                scroll_chunks,
+                recycle_chunks,
+                move_player,
+                spawn_waves,
+                move_enemies,
+                crash,
+                restart,
+                update_hud,
+            )
+                .chain()
+                .run_if(in_state(DemoState::Playing)),
+        )
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    commands.spawn(Camera2d);
+    commands.insert_resource(Waves::load(&asset_server, TIMELINE, vec![ENEMY]));
+
+    for start_x in [0., WIN_W] {
+        commands.spawn((
+            Sprite::from_image(asset_server.load("small_bg.png")),
+            Transform::from_xyz(start_x, 0., -1.),
+            Background,
+        ));
+    }
+
+    let brick_sheet = BrickSheet {
+        image: asset_server.load("bricks.png"),
+        layout: texture_atlases.add(TextureAtlasLayout::from_grid(
+            UVec2::splat(TILE_SIZE),
+            4,
+            1,
+            None,
+            None,
+        )),
+    };
+    let pool = Pool::<Brick>::spawn(&mut commands, BRICK_POOL_SIZE, || {
+        (
+            Sprite::from_atlas_image(
+                brick_sheet.image.clone(),
+                TextureAtlas {
+                    layout: brick_sheet.layout.clone(),
+                    index: 0,
+                },
+            ),
+            Transform::from_xyz(0., 0., 1.),
+            Brick,
+        )
+    });
+    commands.insert_resource(pool);
+    commands.insert_resource(brick_sheet);
+
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 24.,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        },
+        Hud,
+    ));
+}
+
+fn spawn_player(
+    mut commands: Commands,
+    sheet: Res<CharacterSheet>,
+    selected: Res<SelectedCharacter>,
+) {
+    commands.spawn((
+        sheet.sprite(**selected),
+        Transform {
+            translation: Vec3::new(-WIN_W / 3., 0., 900.),
+            ..default()
+        },
+        Velocity::new(),
+        selected.stats(),
+        Player,
+    ));
+}
+
+fn speed_up(time: Res<Time>, mut run: ResMut<Run>) {
+    if run.over {
+        return;
+    }
+    run.speed = (run.speed + SPEED_RAMP * time.delta_secs()).min(MAX_SPEED);
+    run.distance += run.speed * time.delta_secs();
+}
+
+/// The same wraparound as bv08, at a fraction of the run's speed
+fn scroll_bg(
+    time: Res<Time>,
+    run: Res<Run>,
+    mut bg: Query<&mut Transform, (With<Background>, Without<Player>)>,
+) {
+    if run.over {
+        return;
+    }
+    let deltat = time.delta_secs();
+    for mut bt in bg.iter_mut() {
+        bt.translation.x -= run.speed * PARALLAX * deltat;
+        if bt.translation.x < -WIN_W {
+            bt.translation += Vec3::new(WIN_W * 2., 0., 0.);
+        }
+    }
+}
+
+/// Lay down random templates until the course reaches past the lookahead
+fn build_chunks(
+    mut commands: Commands,
+    mut run: ResMut<Run>,
+    mut pool: ResMut<Pool<Brick>>,
+    mut bricks: Query<(&mut Transform, &mut Sprite), With<Brick>>,
+) {
+    let tile = TILE_SIZE as f32;
+    while run.next_chunk_x < WIN_W / 2. + LOOKAHEAD {
+        let index = run.rng.random_range(0..TEMPLATES.len());
+        let template = &TEMPLATES[index];
+        let left = run.next_chunk_x;
+
+        let mut chunk = Chunk {
+            right_edge: left + template[0].len() as f32 * tile,
+            bricks: Vec::new(),
+        };
+        for (row, line) in template.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                if c != '#' {
+                    continue;
+                }
+                let Some(brick) = pool.take(&mut commands) else {
+                    warn!("Out of bricks, make the pool bigger");
+                    continue;
+                };
+                if let Ok((mut transform, mut sprite)) = bricks.get_mut(brick) {
+                    transform.translation.x = left + (col as f32 + 0.5) * tile;
+                    transform.translation.y = WIN_H / 2. - (row as f32 + 0.5) * tile;
+                    if let Some(atlas) = &mut sprite.texture_atlas {
+                        atlas.index = (row + col) % 4;
+                    }
+                }
+                chunk.bricks.push(brick);
+            }
+        }
+
+        run.next_chunk_x = chunk.right_edge;
+        run.chunks.push_back(chunk);
+    }
+}
+
+fn scroll_chunks(
+    time: Res<Time>,
+    mut run: ResMut<Run>,
+    mut bricks: Query<&mut Transform, (With<Brick>, Without<Pooled>)>,
+) {
+    if run.over {
+        return;
+    }
+    let shift = run.speed * time.delta_secs();
+    for mut transform in bricks.iter_mut() {
+        transform.translation.x -= shift;
+    }
+    run.next_chunk_x -= shift;
+    for chunk in run.chunks.iter_mut() {
+        chunk.right_edge -= shift;
+    }
+}
+
+/// Hand the bricks of chunks that have gone off the left back to the pool
+fn recycle_chunks(mut commands: Commands, mut run: ResMut<Run>, mut pool: ResMut<Pool<Brick>>) {
+    while run
+        .chunks
+        .front()
+        .is_some_and(|chunk| chunk.right_edge < -WIN_W / 2.)
+    {
+        if let Some(chunk) = run.chunks.pop_front() {
+            for brick in chunk.bricks {
+                pool.release(&mut commands, brick);
+            }
+        }
+    }
+}
+
+fn move_player(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    run: Res<Run>,
+    player: Single<(&mut Transform, &mut Velocity, &MovementStats), With<Player>>,
+) {
+    if run.over {
+        return;
+    }
+    let (mut transform, mut velocity, stats) = player.into_inner();
+
+    let mut dir = Vec2::ZERO;
+
+    if input.pressed(KeyCode::KeyA) {
+        dir.x -= 1.;
+    }
+
+    if input.pressed(KeyCode::KeyD) {
+        dir.x += 1.;
+    }
+
+    if input.pressed(KeyCode::KeyW) {
+        dir.y += 1.;
+    }
+
+    if input.pressed(KeyCode::KeyS) {
+        dir.y -= 1.;
+    }
+
+    let deltat = time.delta_secs();
+    let flap = input.just_pressed(KeyCode::KeyW);
+
+    **velocity = stats.step(**velocity, dir, flap, deltat);
+    let change = **velocity * deltat;
+
+    let max = Vec2::new(
+        WIN_W / 2. - (TILE_SIZE as f32) / 2.,
+        WIN_H / 2. - (TILE_SIZE as f32) / 2.,
+    );
+    let min = max * -1.;
+
+    let moved = transform.translation + change.extend(0.);
+    transform.translation = moved.clamp(min.extend(900.), max.extend(900.));
+
+    if transform.translation.x != moved.x {
+        velocity.x = 0.;
+    }
+    if transform.translation.y != moved.y {
+        velocity.y = 0.;
+    }
+}
+
+fn spawn_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    run: Res<Run>,
+    mut waves: ResMut<Waves>,
+    timelines: Res<Assets<Timeline>>,
+    asset_server: Res<AssetServer>,
+    enemies: Query<(), With<WaveMember>>,
+) {
+    if run.over {
+        return;
+    }
+
+    for spawn in waves.advance(&timelines, time.delta_secs(), run.distance) {
+        if spawn.enemy != ENEMY {
+            // Already reported when the timeline loaded
+            continue;
+        }
+        for member in WaveMember::members(&spawn, ENEMY_RADIUS) {
+            let mut sprite = Sprite::from_image(asset_server.load("red_circle.png"));
+            sprite.custom_size = Some(Vec2::splat(ENEMY_RADIUS * 2.));
+            commands.spawn((
+                sprite,
+                Transform::from_translation(member.position(WIN_W).extend(500.)),
+                member,
+            ));
+        }
+    }
+
+    // The course never ends, so neither do the waves
+    if waves.finished(&timelines) && enemies.is_empty() {
+        waves.restart(run.distance);
+    }
+}
+
+fn move_enemies(
+    mut commands: Commands,
+    time: Res<Time>,
+    run: Res<Run>,
+    mut enemies: Query<(Entity, &mut Transform, &mut WaveMember)>,
+) {
+    if run.over {
+        return;
+    }
+    for (entity, mut transform, mut member) in enemies.iter_mut() {
+        member.age += time.delta_secs();
+        transform.translation = member.position(WIN_W).extend(transform.translation.z);
+
+        if member.gone(WIN_W) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Touching a brick or an enemy ends the run
+fn crash(
+    mut run: ResMut<Run>,
+    player: Single<&Transform, With<Player>>,
+    bricks: Query<&Transform, (With<Brick>, Without<Pooled>, Without<Player>)>,
+    enemies: Query<(&Transform, &WaveMember), Without<Player>>,
+) {
+    if run.over {
+        return;
+    }
+
+    let half = TILE_SIZE as f32 / 2.;
+    let hit = bricks.iter().any(|brick| {
+        let nearest = player.translation.truncate().clamp(
+            brick.translation.truncate() - half,
+            brick.translation.truncate() + half,
+        );
+        nearest.distance(player.translation.truncate()) < PLAYER_RADIUS
+    }) || enemies.iter().any(|(enemy, member)| {
+        enemy
+            .translation
+            .truncate()
+            .distance(player.translation.truncate())
+            < PLAYER_RADIUS + member.radius
+    });
+    if hit {
+        run.over = true;
+        run.best = run.best.max(run.distance);
+        info!("Crashed after {:.0} m", run.distance / TILE_SIZE as f32);
+    }
+}
+
+/// R runs the same course again from the start
+fn restart(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut run: ResMut<Run>,
+    mut waves: ResMut<Waves>,
+    mut pool: ResMut<Pool<Brick>>,
+    player: Single<(&mut Transform, &mut Velocity), With<Player>>,
+    enemies: Query<Entity, With<WaveMember>>,
+) {
+    if !run.over || !input.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+
+    for enemy in &enemies {
+        commands.entity(enemy).despawn();
+    }
+    waves.restart(0.);
+
+    for chunk in run.chunks.drain(..) {
+        for brick in chunk.bricks {
+            pool.release(&mut commands, brick);
+        }
+    }
+    *run = Run::new(run.seed, run.best);
+
+    let (mut transform, mut velocity) = player.into_inner();
+    transform.translation = Vec3::new(-WIN_W / 3., 0., 900.);
+    **velocity = Vec2::ZERO;
+}
+
+fn update_hud(run: Res<Run>, mut hud: Single<&mut Text, With<Hud>>) {
+    // A tile is a meter
+    let meters = |distance: f32| distance / TILE_SIZE as f32;
+    ***hud = if run.over {
+        format!(
+            "CRASHED  Distance: {:.0} m  Best: {:.0} m  (R to run again)",
+            meters(run.distance),
+            meters(run.best)
+        )
+    } else {
+        format!(
+            "Distance: {:.0} m  Best: {:.0} m  Speed: {:.0}",
+            meters(run.distance),
+            meters(run.best),
+            run.speed
+        )
+    };
+}
@@ -0,0 +1,29 @@
+use bevy::{prelude::*, window::PresentMode};
+use bevy_demos::topdown::TopDownPlugin;
+
+const TITLE: &str = "bv14 Top-down";
+const WIN_W: f32 = 1280.;
+const WIN_H: f32 = 720.;
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::Srgba(Srgba::gray(0.25))))
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: TITLE.into(),
+                resolution: (WIN_W, WIN_H).into(),
+                present_mode: PresentMode::AutoVsync,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins(TopDownPlugin {
+            level: "levels/courtyard.level.ron",
+        })
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2d);
+}
@@ -0,0 +1,379 @@
+//! The four flyers drawn in birds.png, and a screen to pick one of them
+//!
+//! Add `CharacterSelectPlugin` to an example and it starts in
+//! `DemoState::CharacterSelect`. Once a character is picked the state moves
+//! to `DemoState::Playing`, and `SelectedCharacter` says which one it was.
+
+use bevy::prelude::*;
+
+/// Size of one frame of birds.png
+const SHEET_TILE: u32 = 100;
+const SELECTED_COLOR: Color = Color::srgb(1., 0.85, 0.1);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PlayerType {
+    #[default]
+    Bird,
+    Plane,
+    Ufo,
+    Helicopter,
+}
+
+impl PlayerType {
+    pub const ALL: [PlayerType; 4] = [
+        PlayerType::Bird,
+        PlayerType::Plane,
+        PlayerType::Ufo,
+        PlayerType::Helicopter,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlayerType::Bird => "Bird",
+            PlayerType::Plane => "Plane",
+            PlayerType::Ufo => "UFO",
+            PlayerType::Helicopter => "Helicopter",
+        }
+    }
+
+    /// Frame of birds.png, which has the four drawn in this order
+    pub fn atlas_index(self) -> usize {
+        self as usize
+    }
+
+    pub fn stats(self) -> MovementStats {
+        match self {
+            // Falls unless it keeps flapping
+            PlayerType::Bird => MovementStats {
+                speed: 400.,
+                accel: 3000.,
+                drag: 1500.,
+                locomotion: Locomotion::Gravity {
+                    gravity: 1500.,
+                    flap: 550.,
+                },
+            },
+            // Fast, but slow to change direction
+            PlayerType::Plane => MovementStats {
+                speed: 650.,
+                accel: 1800.,
+                drag: 600.,
+                locomotion: Locomotion::Flight,
+            },
+            // Stops and starts on a dime
+            PlayerType::Ufo => MovementStats {
+                speed: 450.,
+                accel: 9000.,
+                drag: 9000.,
+                locomotion: Locomotion::Flight,
+            },
+            // Slow but steady
+            PlayerType::Helicopter => MovementStats {
+                speed: 350.,
+                accel: 2500.,
+                drag: 4000.,
+                locomotion: Locomotion::Flight,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Locomotion {
+    /// Moves freely in any direction
+    Flight,
+    /// Pulled down by `gravity`, and jumps up at `flap` speed when flapping
+    Gravity { gravity: f32, flap: f32 },
+}
+
+/// How a character moves
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct MovementStats {
+    /// Top speed
+    pub speed: f32,
+    /// Speed gained per second while a direction is held
+    pub accel: f32,
+    /// Speed lost per second while nothing is held
+    pub drag: f32,
+    pub locomotion: Locomotion,
+}
+
+impl MovementStats {
+    /// Velocity after `deltat` seconds of holding `dir`
+    ///
+    /// `flap` is whether the jump key was pressed this frame, which only
+    /// matters to characters that fall.
+    pub fn step(&self, velocity: Vec2, dir: Vec2, flap: bool, deltat: f32) -> Vec2 {
+        match self.locomotion {
+            Locomotion::Flight => self.accelerate(velocity, dir, deltat),
+            Locomotion::Gravity {
+                gravity,
+                flap: flap_speed,
+            } => {
+                // Steer sideways only, gravity and flapping take care of up and down
+                let x = self.accelerate(Vec2::new(velocity.x, 0.), Vec2::new(dir.x, 0.), deltat);
+                let y = if flap {
+                    flap_speed
+                } else {
+                    velocity.y - gravity * deltat
+                };
+                Vec2::new(x.x, y)
+            }
+        }
+    }
+
+    fn accelerate(&self, velocity: Vec2, dir: Vec2, deltat: f32) -> Vec2 {
+        let accel = self.accel * deltat;
+        let drag = self.drag * deltat;
+
+        if dir.length() > 0. {
+            (velocity + (dir.normalize_or_zero() * accel)).clamp_length_max(self.speed)
+        } else if velocity.length() > drag {
+            velocity + (velocity.normalize_or_zero() * -drag)
+        } else {
+            Vec2::ZERO
+        }
+    }
+}
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DemoState {
+    #[default]
+    CharacterSelect,
+    Playing,
+}
+
+/// The character picked on the select screen
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct SelectedCharacter(pub PlayerType);
+
+/// birds.png and its layout, for spawning any of the characters
+#[derive(Resource)]
+pub struct CharacterSheet {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+}
+
+impl CharacterSheet {
+    pub fn sprite(&self, player_type: PlayerType) -> Sprite {
+        Sprite::from_atlas_image(self.image.clone(), self.atlas(player_type))
+    }
+
+    fn atlas(&self, player_type: PlayerType) -> TextureAtlas {
+        TextureAtlas {
+            layout: self.layout.clone(),
+            index: player_type.atlas_index(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct SelectScreen;
+
+/// One column of the select screen
+#[derive(Component)]
+struct SelectOption(PlayerType);
+
+pub struct CharacterSelectPlugin;
+impl Plugin for CharacterSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<DemoState>()
+            .init_resource::<SelectedCharacter>()
+            .add_systems(PreStartup, load_sheet)
+            .add_systems(OnEnter(DemoState::CharacterSelect), setup_select_screen)
+            .add_systems(
+                Update,
+                (choose_character, highlight_selection)
+                    .chain()
+                    .run_if(in_state(DemoState::CharacterSelect)),
+            )
+            .add_systems(OnExit(DemoState::CharacterSelect), despawn_select_screen);
+    }
+}
+
+fn load_sheet(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let layout = TextureAtlasLayout::from_grid(UVec2::splat(SHEET_TILE), 2, 2, None, None);
+    commands.insert_resource(CharacterSheet {
+        image: asset_server.load("birds.png"),
+        layout: texture_atlases.add(layout),
+    });
+}
+
+fn setup_select_screen(mut commands: Commands, sheet: Res<CharacterSheet>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(30.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.6)),
+            SelectScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Choose your flyer"),
+                TextFont {
+                    font_size: 40.,
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(40.),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for player_type in PlayerType::ALL {
+                        spawn_option(row, &sheet, player_type);
+                    }
+                });
+
+            parent.spawn((
+                Text::new("A/D or Left/Right to choose, Enter or Space to start"),
+                TextFont {
+                    font_size: 18.,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_option(row: &mut ChildSpawnerCommands, sheet: &CharacterSheet, player_type: PlayerType) {
+    let stats = player_type.stats();
+    let movement = match stats.locomotion {
+        Locomotion::Flight => "Flies freely",
+        Locomotion::Gravity { .. } => "Flaps with W",
+    };
+
+    row.spawn((
+        Node {
+            width: Val::Px(200.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(8.),
+            padding: UiRect::all(Val::Px(10.)),
+            border: UiRect::all(Val::Px(3.)),
+            ..default()
+        },
+        BorderColor(Color::NONE),
+        SelectOption(player_type),
+    ))
+    .with_children(|option| {
+        option.spawn((
+            ImageNode::from_atlas_image(sheet.image.clone(), sheet.atlas(player_type)),
+            Node {
+                width: Val::Px(SHEET_TILE as f32),
+                height: Val::Px(SHEET_TILE as f32),
+                ..default()
+            },
+        ));
+        option.spawn((
+            Text::new(player_type.name()),
+            TextFont {
+                font_size: 24.,
+                ..default()
+            },
+        ));
+        option.spawn((
+            Text::new(format!(
+                "Speed {:.0}\nAccel {:.0}\nDrag {:.0}\n{}",
+                stats.speed, stats.accel, stats.drag, movement
+            )),
+            TextFont {
+                font_size: 16.,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+        ));
+    });
+}
+
+fn choose_character(
+    input: Res<ButtonInput<KeyCode>>,
+    mut selected: ResMut<SelectedCharacter>,
+    mut next_state: ResMut<NextState<DemoState>>,
+) {
+    let index = selected.atlas_index();
+    let count = PlayerType::ALL.len();
+
+    if input.any_just_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
+        **selected = PlayerType::ALL[(index + count - 1) % count];
+    }
+    if input.any_just_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
+        **selected = PlayerType::ALL[(index + 1) % count];
+    }
+    if input.any_just_pressed([KeyCode::Enter, KeyCode::Space]) {
+        info!("Playing as {}", selected.name());
+        next_state.set(DemoState::Playing);
+    }
+}
+
+fn highlight_selection(
+    selected: Res<SelectedCharacter>,
+    mut options: Query<(&SelectOption, &mut BorderColor)>,
+) {
+    for (option, mut border) in options.iter_mut() {
+        border.0 = if option.0 == **selected {
+            SELECTED_COLOR
+        } else {
+            Color::NONE
+        };
+    }
+}
+
+fn despawn_select_screen(mut commands: Commands, screen: Query<Entity, With<SelectScreen>>) {
+    for entity in &screen {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_type_has_its_own_frame() {
+        for (i, player_type) in PlayerType::ALL.iter().enumerate() {
+            assert_eq!(player_type.atlas_index(), i);
+        }
+    }
+
+    #[test]
+    fn flight_stops_at_top_speed() {
+        let stats = PlayerType::Plane.stats();
+        let mut velocity = Vec2::ZERO;
+        for _ in 0..100 {
+            velocity = stats.step(velocity, Vec2::X, false, 0.1);
+        }
+        assert_eq!(velocity, Vec2::new(stats.speed, 0.));
+    }
+
+    #[test]
+    fn drag_brings_flight_to_rest() {
+        let stats = PlayerType::Ufo.stats();
+        let velocity = stats.step(Vec2::new(100., 0.), Vec2::ZERO, false, 0.1);
+        assert_eq!(velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn gravity_pulls_down_until_flapping() {
+        let stats = PlayerType::Bird.stats();
+        let Locomotion::Gravity { gravity, flap } = stats.locomotion else {
+            panic!("the bird should fall");
+        };
+
+        let falling = stats.step(Vec2::ZERO, Vec2::Y, false, 0.1);
+        assert_eq!(falling.y, -gravity * 0.1);
+        assert_eq!(stats.step(falling, Vec2::ZERO, true, 0.1).y, flap);
+    }
+}
@@ -1,3 +1,8 @@
+pub mod characters;
+pub mod pool;
+pub mod timeline;
+pub mod topdown;
+
 #[cfg(test)]
 mod tests {
     #[test]
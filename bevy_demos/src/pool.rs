@@ -0,0 +1,123 @@
+//! Entities that are spawned once and reused, rather than spawned and
+//! despawned over and over
+//!
+//! Free entities carry `Pooled` and are hidden, so queries for the ones in
+//! play should filter on `Without<Pooled>`.
+
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+/// On every entity a pool is holding back
+#[derive(Component)]
+pub struct Pooled;
+
+/// A fixed number of entities of one kind, told apart by the marker `T`
+#[derive(Resource)]
+pub struct Pool<T> {
+    free: Vec<Entity>,
+    size: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Pool<T> {
+    /// Spawns `size` entities made by `bundle`, all free to start with
+    pub fn spawn<B: Bundle>(commands: &mut Commands, size: usize, bundle: impl Fn() -> B) -> Self {
+        let free = (0..size)
+            .map(|_| commands.spawn((bundle(), Pooled, Visibility::Hidden)).id())
+            .collect();
+        Self {
+            free,
+            size,
+            marker: PhantomData,
+        }
+    }
+
+    /// A free entity, shown and ready to be placed, or `None` if every one is
+    /// in use
+    pub fn take(&mut self, commands: &mut Commands) -> Option<Entity> {
+        let entity = self.free.pop()?;
+        commands
+            .entity(entity)
+            .remove::<Pooled>()
+            .insert(Visibility::Inherited);
+        Some(entity)
+    }
+
+    /// Hides `entity` and makes it free again
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        // Two things can finish with the same entity in one frame
+        if self.free.contains(&entity) {
+            return;
+        }
+        commands.entity(entity).insert((Pooled, Visibility::Hidden));
+        self.free.push(entity);
+    }
+
+    pub fn free(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn in_use(&self) -> usize {
+        self.size - self.free.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Default)]
+    struct Shot;
+
+    fn pool(world: &mut World, size: usize) -> Pool<Shot> {
+        let pool = Pool::spawn(&mut world.commands(), size, Shot::default);
+        world.flush();
+        pool
+    }
+
+    #[test]
+    fn spawns_everything_free() {
+        let mut world = World::new();
+        let pool = pool(&mut world, 4);
+
+        assert_eq!(pool.free(), 4);
+        let pooled = world
+            .query_filtered::<&Visibility, (With<Shot>, With<Pooled>)>()
+            .iter(&world)
+            .filter(|visibility| **visibility == Visibility::Hidden)
+            .count();
+        assert_eq!(pooled, 4);
+    }
+
+    #[test]
+    fn takes_until_empty_then_reuses() {
+        let mut world = World::new();
+        let mut pool = pool(&mut world, 2);
+
+        let first = pool.take(&mut world.commands()).unwrap();
+        let second = pool.take(&mut world.commands()).unwrap();
+        assert!(pool.take(&mut world.commands()).is_none());
+        world.flush();
+        assert!(!world.entity(first).contains::<Pooled>());
+        assert_eq!(pool.in_use(), 2);
+
+        pool.release(&mut world.commands(), second);
+        assert_eq!(pool.take(&mut world.commands()), Some(second));
+        // Nothing new was spawned along the way
+        assert_eq!(world.query::<&Shot>().iter(&world).count(), 2);
+    }
+
+    #[test]
+    fn releasing_twice_frees_once() {
+        let mut world = World::new();
+        let mut pool = pool(&mut world, 1);
+
+        let shot = pool.take(&mut world.commands()).unwrap();
+        pool.release(&mut world.commands(), shot);
+        pool.release(&mut world.commands(), shot);
+        world.flush();
+
+        assert_eq!(pool.free(), 1);
+        assert!(world.entity(shot).contains::<Pooled>());
+    }
+}
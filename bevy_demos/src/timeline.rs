@@ -0,0 +1,610 @@
+//! Text files that schedule enemy spawns for the scrolling examples
+//!
+//! One spawn per line, blank lines and `#` comments ignored:
+//!
+//! ```text
+//! # when   enemy   path              formation   count
+//! @1s      drone   line(200)         trail(0.4)  5
+//! @7.5s    drone   sine(0, 150, 2)   trail(0.3)  8
+//! @1800px  heavy   curve(300, -250)  vee(70)     5
+//! ```
+//!
+//! - `@<n>s` spawns that many seconds in, `@<n>px` once the screen has
+//!   scrolled that far
+//! - the enemy type is any name, checked against the ones a game knows with
+//!   `Timeline::validate`
+//! - `line(y)` crosses at a height, `sine(y, amplitude, period)` bobs around
+//!   one, and `curve(from_y, to_y)` sweeps from one height to another
+//! - `trail(seconds)` sends the group one after another, `column(gap)` side by
+//!   side in a vertical line and `vee(gap)` in a V with the leader in front
+//!
+//! A game plays one by inserting `Waves`, advancing it each frame and spawning
+//! a `WaveMember` for each enemy of every spawn that comes due.
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use std::{f32::consts::TAU, fmt, str::FromStr};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SpawnTrigger {
+    /// Seconds since the timeline started
+    Time(f32),
+    /// How far the screen has scrolled
+    Distance(f32),
+}
+
+/// How an enemy crosses the screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EntryPath {
+    Line { y: f32 },
+    Sine { y: f32, amplitude: f32, period: f32 },
+    Curve { from_y: f32, to_y: f32 },
+}
+
+impl EntryPath {
+    /// Height `age` seconds after entering, `across` being how far over the
+    /// screen the enemy is from 0 to 1
+    pub fn y(self, age: f32, across: f32) -> f32 {
+        match self {
+            EntryPath::Line { y } => y,
+            EntryPath::Sine {
+                y,
+                amplitude,
+                period,
+            } => y + amplitude * (age / period * TAU).sin(),
+            // Ease in and out of the turn
+            EntryPath::Curve { from_y, to_y } => {
+                let t = across.clamp(0., 1.);
+                from_y + (to_y - from_y) * t * t * (3. - 2. * t)
+            }
+        }
+    }
+}
+
+/// How the enemies of one spawn are arranged
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Formation {
+    /// One after another, this many seconds apart
+    Trail(f32),
+    /// Stacked vertically, this far apart
+    Column(f32),
+    /// A V pointing forward, each rank this far behind and apart
+    Vee(f32),
+}
+
+impl Formation {
+    /// Seconds to wait before member `index` of `count` enters, and how far it
+    /// is from the path, with positive x further behind
+    pub fn place(self, index: u32, count: u32) -> (f32, Vec2) {
+        let middle = (count.max(1) - 1) as f32 / 2.;
+        let from_middle = index as f32 - middle;
+        match self {
+            Formation::Trail(spacing) => (index as f32 * spacing, Vec2::ZERO),
+            Formation::Column(gap) => (0., Vec2::new(0., from_middle * gap)),
+            Formation::Vee(gap) => (0., Vec2::new(from_middle.abs() * gap, from_middle * gap)),
+        }
+    }
+}
+
+/// One line of a timeline
+#[derive(Clone, PartialEq, Debug)]
+pub struct Spawn {
+    /// Where in the file it was written, counting from 1
+    pub line: usize,
+    pub trigger: SpawnTrigger,
+    pub enemy: String,
+    pub path: EntryPath,
+    pub formation: Formation,
+    pub count: u32,
+}
+
+#[derive(Asset, TypePath, Clone, PartialEq, Debug, Default)]
+pub struct Timeline {
+    pub spawns: Vec<Spawn>,
+}
+
+/// A problem with a timeline, and the line it's on
+#[derive(Clone, PartialEq, Debug)]
+pub struct TimelineError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TimelineError {}
+
+impl FromStr for Timeline {
+    type Err = TimelineError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut spawns = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let content = line.split('#').next().unwrap_or_default().trim();
+            if content.is_empty() {
+                continue;
+            }
+            let spawn = parse_spawn(content, line_number).map_err(|message| TimelineError {
+                line: line_number,
+                message,
+            })?;
+            spawns.push(spawn);
+        }
+        Ok(Self { spawns })
+    }
+}
+
+impl Timeline {
+    /// Every spawn of an enemy type not in `known`
+    pub fn validate(&self, known: &[&str]) -> Vec<TimelineError> {
+        self.spawns
+            .iter()
+            .filter(|spawn| !known.contains(&spawn.enemy.as_str()))
+            .map(|spawn| TimelineError {
+                line: spawn.line,
+                message: format!(
+                    "unknown enemy type `{}`, expected one of {}",
+                    spawn.enemy,
+                    known.join(", ")
+                ),
+            })
+            .collect()
+    }
+}
+
+/// Splits a line into words, keeping anything in parentheses together
+fn words(content: &str) -> Result<Vec<&str>, String> {
+    let mut words = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in content.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(String::from("`)` without a `(`")),
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if c.is_whitespace() && depth == 0 {
+            if let Some(s) = start.take() {
+                words.push(&content[s..i]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if depth > 0 {
+        return Err(String::from("`(` without a `)`"));
+    }
+    if let Some(s) = start {
+        words.push(&content[s..]);
+    }
+    Ok(words)
+}
+
+fn parse_spawn(content: &str, line: usize) -> Result<Spawn, String> {
+    let words = words(content)?;
+    let [when, enemy, path, formation, count] = words[..] else {
+        return Err(format!(
+            "expected `@when enemy path formation count`, found {} words",
+            words.len()
+        ));
+    };
+
+    Ok(Spawn {
+        line,
+        trigger: parse_trigger(when)?,
+        enemy: enemy.to_string(),
+        path: parse_path(path)?,
+        formation: parse_formation(formation)?,
+        count: count
+            .parse()
+            .map_err(|_| format!("`{}` isn't a whole number of enemies", count))?,
+    })
+}
+
+fn parse_trigger(word: &str) -> Result<SpawnTrigger, String> {
+    let amount = word
+        .strip_prefix('@')
+        .ok_or_else(|| format!("`{}` should start with @", word))?;
+    if let Some(pixels) = amount.strip_suffix("px") {
+        Ok(SpawnTrigger::Distance(number(pixels)?))
+    } else if let Some(seconds) = amount.strip_suffix('s') {
+        Ok(SpawnTrigger::Time(number(seconds)?))
+    } else {
+        Err(format!("`{}` should end in s or px", word))
+    }
+}
+
+/// The name and arguments of `name(a, b, ...)`
+fn call(word: &str) -> Result<(&str, Vec<f32>), String> {
+    let (name, rest) = word
+        .split_once('(')
+        .ok_or_else(|| format!("`{}` is missing its arguments", word))?;
+    let args = rest
+        .strip_suffix(')')
+        .ok_or_else(|| format!("`{}` should end with `)`", word))?;
+    let args = args
+        .split(',')
+        .filter(|arg| !arg.trim().is_empty())
+        .map(number)
+        .collect::<Result<_, _>>()?;
+    Ok((name, args))
+}
+
+fn parse_path(word: &str) -> Result<EntryPath, String> {
+    match call(word)? {
+        ("line", args) => match args[..] {
+            [y] => Ok(EntryPath::Line { y }),
+            _ => Err(String::from("line takes (y)")),
+        },
+        ("sine", args) => match args[..] {
+            [y, amplitude, period] if period > 0. => Ok(EntryPath::Sine {
+                y,
+                amplitude,
+                period,
+            }),
+            _ => Err(String::from("sine takes (y, amplitude, period above 0)")),
+        },
+        ("curve", args) => match args[..] {
+            [from_y, to_y] => Ok(EntryPath::Curve { from_y, to_y }),
+            _ => Err(String::from("curve takes (from_y, to_y)")),
+        },
+        (name, _) => Err(format!(
+            "unknown path `{}`, expected line, sine or curve",
+            name
+        )),
+    }
+}
+
+fn parse_formation(word: &str) -> Result<Formation, String> {
+    let (name, args) = call(word)?;
+    let [spacing] = args[..] else {
+        return Err(format!("{} takes one spacing", name));
+    };
+    match name {
+        "trail" => Ok(Formation::Trail(spacing)),
+        "column" => Ok(Formation::Column(spacing)),
+        "vee" => Ok(Formation::Vee(spacing)),
+        _ => Err(format!(
+            "unknown formation `{}`, expected trail, column or vee",
+            name
+        )),
+    }
+}
+
+fn number(word: &str) -> Result<f32, String> {
+    word.trim()
+        .parse()
+        .map_err(|_| format!("`{}` isn't a number", word.trim()))
+}
+
+/// Which spawns of a timeline have happened so far
+#[derive(Default)]
+pub struct TimelineProgress {
+    fired: Vec<bool>,
+}
+
+impl TimelineProgress {
+    /// Spawns that have come due since the last call, in file order
+    pub fn due<'a>(&mut self, timeline: &'a Timeline, time: f32, distance: f32) -> Vec<&'a Spawn> {
+        self.fired.resize(timeline.spawns.len(), false);
+
+        let mut due = Vec::new();
+        for (spawn, fired) in timeline.spawns.iter().zip(self.fired.iter_mut()) {
+            let reached = match spawn.trigger {
+                SpawnTrigger::Time(at) => time >= at,
+                SpawnTrigger::Distance(at) => distance >= at,
+            };
+            if reached && !*fired {
+                *fired = true;
+                due.push(spawn);
+            }
+        }
+        due
+    }
+
+    pub fn finished(&self, timeline: &Timeline) -> bool {
+        self.fired.len() >= timeline.spawns.len() && self.fired.iter().all(|fired| *fired)
+    }
+
+    /// Start from the top again
+    pub fn reset(&mut self) {
+        self.fired.clear();
+    }
+}
+
+/// Loads `.timeline` files, failing with the line of the first mistake
+#[derive(Default)]
+pub struct TimelineLoader;
+
+impl AssetLoader for TimelineLoader {
+    type Asset = Timeline;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(std::str::from_utf8(&bytes)?.parse::<Timeline>()?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["timeline"]
+    }
+}
+
+/// How fast enemies cross the screen along their paths
+pub const WAVE_SPEED: f32 = 250.;
+
+/// One enemy of a spawn, following its path across the screen
+#[derive(Component, Clone, Debug)]
+pub struct WaveMember {
+    pub spawn: Spawn,
+    /// Seconds since entering, negative while still waiting its turn
+    pub age: f32,
+    /// Where it sits in its formation
+    pub offset: Vec2,
+    pub radius: f32,
+}
+
+impl WaveMember {
+    /// Every enemy of `spawn`, placed in its formation
+    pub fn members(spawn: &Spawn, radius: f32) -> impl Iterator<Item = WaveMember> + '_ {
+        (0..spawn.count).map(move |index| {
+            let (delay, offset) = spawn.formation.place(index, spawn.count);
+            WaveMember {
+                spawn: spawn.clone(),
+                age: -delay,
+                offset,
+                radius,
+            }
+        })
+    }
+
+    /// Where it is relative to the middle of a view `width` wide, having come
+    /// in from the right edge
+    pub fn position(&self, width: f32) -> Vec2 {
+        let travelled = WAVE_SPEED * self.age;
+        let x = width / 2. + self.radius + self.offset.x - travelled;
+        let y = self.spawn.path.y(self.age, travelled / width) + self.offset.y;
+        Vec2::new(x, y)
+    }
+
+    /// Whether it has crossed out of the left edge of the view
+    pub fn gone(&self, width: f32) -> bool {
+        self.position(width).x < -width / 2. - self.radius
+    }
+}
+
+/// The timeline a game is playing, and how far through it is
+#[derive(Resource)]
+pub struct Waves {
+    /// Asset path, for error messages
+    pub path: &'static str,
+    handle: Handle<Timeline>,
+    /// Enemy types the game can spawn
+    known: Vec<&'static str>,
+    /// Seconds since the timeline started
+    pub clock: f32,
+    /// Scroll distance when the timeline started
+    start_distance: f32,
+    progress: TimelineProgress,
+}
+
+impl Waves {
+    pub fn load(asset_server: &AssetServer, path: &'static str, known: Vec<&'static str>) -> Self {
+        Self {
+            path,
+            handle: asset_server.load(path),
+            known,
+            clock: 0.,
+            start_distance: 0.,
+            progress: TimelineProgress::default(),
+        }
+    }
+
+    /// Move the clock on by `deltat`, with the screen having scrolled
+    /// `distance` in all, and return the spawns that came due
+    ///
+    /// Nothing happens until the timeline has loaded.
+    pub fn advance(
+        &mut self,
+        timelines: &Assets<Timeline>,
+        deltat: f32,
+        distance: f32,
+    ) -> Vec<Spawn> {
+        let Some(timeline) = timelines.get(&self.handle) else {
+            return Vec::new();
+        };
+        self.clock += deltat;
+        self.progress
+            .due(timeline, self.clock, distance - self.start_distance)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Whether every spawn has come due
+    pub fn finished(&self, timelines: &Assets<Timeline>) -> bool {
+        timelines
+            .get(&self.handle)
+            .is_some_and(|timeline| self.progress.finished(timeline))
+    }
+
+    /// Play the timeline again from the top, counting scroll distance from
+    /// `distance`
+    pub fn restart(&mut self, distance: f32) {
+        self.clock = 0.;
+        self.start_distance = distance;
+        self.progress.reset();
+    }
+}
+
+pub struct TimelinePlugin;
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Timeline>()
+            .init_asset_loader::<TimelineLoader>()
+            .add_systems(
+                Update,
+                report_unknown_enemies.run_if(resource_exists::<Waves>),
+            );
+    }
+}
+
+/// Point out enemy types the timeline uses that the game doesn't have
+fn report_unknown_enemies(
+    mut events: EventReader<AssetEvent<Timeline>>,
+    timelines: Res<Assets<Timeline>>,
+    waves: Res<Waves>,
+) {
+    for event in events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } = event
+            && *id == waves.handle.id()
+            && let Some(timeline) = timelines.get(*id)
+        {
+            for error in timeline.validate(&waves.known) {
+                error!("{}: {}", waves.path, error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "
+# when   enemy   path              formation   count
+@1s      drone   line(200)         trail(0.4)  5
+
+@7.5s    drone   sine(0, 150, 2)   trail(0.3)  8  # wobbly
+@1800px  heavy   curve(300, -250)  vee(70)     5
+";
+
+    #[test]
+    fn parses_each_spawn_with_its_line() {
+        let timeline: Timeline = EXAMPLE.parse().unwrap();
+        assert_eq!(timeline.spawns.len(), 3);
+
+        let sine = &timeline.spawns[1];
+        assert_eq!(sine.line, 5);
+        assert_eq!(sine.trigger, SpawnTrigger::Time(7.5));
+        assert_eq!(
+            sine.path,
+            EntryPath::Sine {
+                y: 0.,
+                amplitude: 150.,
+                period: 2.
+            }
+        );
+        assert_eq!(sine.formation, Formation::Trail(0.3));
+        assert_eq!(sine.count, 8);
+
+        let heavy = &timeline.spawns[2];
+        assert_eq!(heavy.trigger, SpawnTrigger::Distance(1800.));
+        assert_eq!(heavy.enemy, "heavy");
+    }
+
+    #[test]
+    fn syntax_errors_name_the_line() {
+        let error = "@1s drone line(0) trail(1) 2\n@2s drone zigzag(1) trail(1) 2"
+            .parse::<Timeline>()
+            .unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(error.message.contains("zigzag"), "{}", error);
+
+        let error = "@1 drone line(0) trail(1) 2"
+            .parse::<Timeline>()
+            .unwrap_err();
+        assert_eq!(error.line, 1);
+    }
+
+    #[test]
+    fn validation_reports_unknown_enemies_by_line() {
+        let timeline: Timeline = EXAMPLE.parse().unwrap();
+        assert!(timeline.validate(&["drone", "heavy"]).is_empty());
+
+        let errors = timeline.validate(&["drone"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 6);
+        assert!(
+            errors[0]
+                .to_string()
+                .starts_with("line 6: unknown enemy type `heavy`")
+        );
+    }
+
+    #[test]
+    fn spawns_come_due_once() {
+        let timeline: Timeline = EXAMPLE.parse().unwrap();
+        let mut progress = TimelineProgress::default();
+
+        assert!(progress.due(&timeline, 0.5, 0.).is_empty());
+        assert_eq!(progress.due(&timeline, 8., 100.).len(), 2);
+        assert!(progress.due(&timeline, 9., 100.).is_empty());
+        assert!(!progress.finished(&timeline));
+
+        let last = progress.due(&timeline, 9., 2000.);
+        assert_eq!(last[0].enemy, "heavy");
+        assert!(progress.finished(&timeline));
+    }
+
+    #[test]
+    fn shipped_timelines_are_valid() {
+        for (file, text) in [
+            ("shmup", include_str!("../assets/waves/shmup.timeline")),
+            ("scroll", include_str!("../assets/waves/scroll.timeline")),
+            (
+                "side_scroll",
+                include_str!("../assets/waves/side_scroll.timeline"),
+            ),
+            ("runner", include_str!("../assets/waves/runner.timeline")),
+        ] {
+            let timeline: Timeline = text.parse().unwrap_or_else(|e| panic!("{}: {}", file, e));
+            assert!(
+                timeline.validate(&["drone", "heavy"]).is_empty(),
+                "{}",
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn members_enter_from_the_right_in_turn() {
+        let timeline: Timeline = "@1s drone line(100) trail(0.5) 3".parse().unwrap();
+        let members: Vec<WaveMember> = WaveMember::members(&timeline.spawns[0], 20.).collect();
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[2].age, -1.);
+
+        let mut leader = members[0].clone();
+        assert_eq!(leader.position(1000.), Vec2::new(520., 100.));
+        assert!(!leader.gone(1000.));
+        leader.age = 1040. / WAVE_SPEED + 0.1;
+        assert!(leader.gone(1000.));
+    }
+
+    #[test]
+    fn formations_spread_around_the_path() {
+        assert_eq!(Formation::Trail(0.5).place(2, 3), (1., Vec2::ZERO));
+        assert_eq!(
+            Formation::Column(10.).place(0, 3),
+            (0., Vec2::new(0., -10.))
+        );
+        // The middle of a V leads
+        assert_eq!(Formation::Vee(10.).place(1, 3), (0., Vec2::ZERO));
+        assert_eq!(Formation::Vee(10.).place(2, 3), (0., Vec2::new(10., 10.)));
+    }
+}
@@ -0,0 +1,676 @@
+//! A top-down game mode: walls, solid objects, y-sorting and 8-way walking
+//!
+//! Levels use the same `*.level.ron` format as the side-scroller in
+//! bevy_project_structure, read from above instead of from the side. Row 0
+//! of `tiles` is the top of the map. Tiles mean the same as they do there:
+//! bricks (`0`-`3`) are walls, `C` a checkpoint, `^` a hazard, `*` something
+//! to collect, `E` an enemy and `N` someone standing around. `O` is a solid
+//! object only the top-down mode draws, and any other character is floor, as
+//! the side-scroller treats it as empty. Fields the top-down mode has no use
+//! for, such as `triggers`, are ignored.
+//!
+//! Add `TopDownPlugin` with the path of a level and a `Camera2d`, and the
+//! plugin spawns the level and a player who walks around it with WASD or
+//! the arrow keys.
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+    transform::TransformSystem,
+};
+use serde::Deserialize;
+use std::f32::consts::FRAC_PI_4;
+
+use crate::characters::{Locomotion, MovementStats};
+
+pub const TILE_SIZE: f32 = 100.;
+
+/// walker.png has a row per facing and a column per walking frame
+const WALKER_FRAMES: usize = 4;
+const WALKER_FACINGS: usize = 8;
+const WALK_FRAME_TIME: f32 = 0.12;
+
+/// Sprites further down the screen are drawn in front, so z shrinks as y
+/// grows. The scale keeps z inside the camera's range for maps of a few
+/// hundred tiles.
+const Y_SORT_BASE: f32 = 500.;
+const Y_SORT_SCALE: f32 = 0.001;
+
+const PLAYER_STATS: MovementStats = MovementStats {
+    speed: 350.,
+    accel: 3000.,
+    drag: 3000.,
+    locomotion: Locomotion::Flight,
+};
+
+/// The parts of a side-scroller level the top-down mode understands
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct TopDownLevel {
+    pub name: String,
+    pub tiles: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TopDownTile {
+    Floor,
+    /// A wall drawn with the given frame of bricks.png
+    Wall(usize),
+    /// Where the player comes back after getting hurt
+    Checkpoint,
+    Hazard,
+    Collectible,
+    Enemy,
+    Npc,
+    /// Something standing on the floor, which you can walk behind
+    Object,
+}
+
+impl TopDownTile {
+    pub fn from_char(c: char) -> Self {
+        match c {
+            '0'..='3' => TopDownTile::Wall(c as usize - '0' as usize),
+            'C' => TopDownTile::Checkpoint,
+            '^' => TopDownTile::Hazard,
+            '*' => TopDownTile::Collectible,
+            'E' => TopDownTile::Enemy,
+            'N' => TopDownTile::Npc,
+            'O' => TopDownTile::Object,
+            _ => TopDownTile::Floor,
+        }
+    }
+}
+
+impl TopDownLevel {
+    /// Width and height in tiles
+    pub fn size(&self) -> (usize, usize) {
+        let width = self.tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+        (width, self.tiles.len())
+    }
+
+    /// Tiles past the end of a short row are floor
+    pub fn tile(&self, col: usize, row: usize) -> TopDownTile {
+        self.tiles
+            .get(row)
+            .and_then(|r| r.chars().nth(col))
+            .map_or(TopDownTile::Floor, TopDownTile::from_char)
+    }
+
+    /// Centre of a tile, with the whole level centred on the origin
+    pub fn tile_position(&self, col: usize, row: usize) -> Vec2 {
+        let (width, height) = self.size();
+        Vec2::new(
+            (col as f32 - (width as f32 - 1.) / 2.) * TILE_SIZE,
+            ((height as f32 - 1.) / 2. - row as f32) * TILE_SIZE,
+        )
+    }
+
+    /// The first open tile, searching from the bottom left
+    pub fn start(&self) -> Option<(usize, usize)> {
+        let (width, height) = self.size();
+        (0..height)
+            .rev()
+            .flat_map(|row| (0..width).map(move |col| (col, row)))
+            .find(|&(col, row)| self.tile(col, row) == TopDownTile::Floor)
+    }
+
+    /// The area the level covers
+    pub fn bounds(&self) -> Rect {
+        let (width, height) = self.size();
+        Rect::from_center_size(
+            Vec2::ZERO,
+            Vec2::new(width as f32, height as f32) * TILE_SIZE,
+        )
+    }
+}
+
+/// Row of walker.png for something moving along `velocity`
+///
+/// Rows go counterclockwise from east: E, NE, N, NW, W, SW, S, SE.
+pub fn facing_row(velocity: Vec2) -> Option<usize> {
+    if velocity == Vec2::ZERO {
+        return None;
+    }
+    let eighths = (velocity.y.atan2(velocity.x) / FRAC_PI_4).round() as i32;
+    Some(eighths.rem_euclid(WALKER_FACINGS as i32) as usize)
+}
+
+/// An axis-aligned box that nothing can walk through, relative to the
+/// entity's translation
+#[derive(Component, Clone, Copy)]
+pub struct Solid {
+    pub offset: Vec2,
+    pub half_size: Vec2,
+}
+
+impl Solid {
+    fn rect(&self, position: Vec2) -> Rect {
+        Rect::from_center_half_size(position + self.offset, self.half_size)
+    }
+}
+
+/// Sets z from how far down the screen the entity's feet are
+#[derive(Component, Clone, Copy)]
+pub struct YSort {
+    /// Distance from the translation down to the feet
+    pub feet: f32,
+}
+
+/// What happens when the player walks onto something
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Touch {
+    Checkpoint,
+    Collect,
+    Hurt,
+}
+
+#[derive(Component)]
+pub struct TopDownPlayer;
+
+/// Where the player goes back to after getting hurt
+#[derive(Resource, Deref, DerefMut)]
+pub struct Respawn(pub Vec2);
+
+/// How many collectibles have been picked up
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct Collected(pub u32);
+
+#[derive(Component, Deref, DerefMut, Default)]
+pub struct Velocity(pub Vec2);
+
+#[derive(Component)]
+pub struct Walker {
+    pub facing: usize,
+    pub frame: usize,
+    timer: Timer,
+}
+
+impl Default for Walker {
+    fn default() -> Self {
+        Self {
+            // Facing the camera
+            facing: 6,
+            frame: 0,
+            timer: Timer::from_seconds(WALK_FRAME_TIME, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct LevelHandle(Handle<TopDownLevel>);
+
+/// Where the current level sits in the world, present once it has spawned
+#[derive(Resource, Deref)]
+pub struct LevelBounds(pub Rect);
+
+#[derive(Default)]
+struct TopDownLevelLoader;
+
+impl AssetLoader for TopDownLevelLoader {
+    type Asset = TopDownLevel;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+pub struct TopDownPlugin {
+    /// Asset path of the level to play
+    pub level: &'static str,
+}
+
+impl Plugin for TopDownPlugin {
+    fn build(&self, app: &mut App) {
+        let level = self.level;
+        app.init_asset::<TopDownLevel>()
+            .init_asset_loader::<TopDownLevelLoader>()
+            .init_resource::<Collected>()
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    commands.insert_resource(LevelHandle(asset_server.load(level)));
+                },
+            )
+            .add_systems(
+                Update,
+                (
+                    spawn_level.run_if(not(resource_exists::<LevelBounds>)),
+                    (move_player, touch_things, animate_walkers, follow_player)
+                        .chain()
+                        .run_if(resource_exists::<LevelBounds>),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                y_sort.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    handle: Res<LevelHandle>,
+    levels: Res<Assets<TopDownLevel>>,
+) {
+    let Some(level) = levels.get(&handle.0) else {
+        return;
+    };
+    info!("Loaded top-down level \"{}\"", level.name);
+
+    let brick_sheet = asset_server.load("bricks.png");
+    let brick_layout = texture_atlases.add(TextureAtlasLayout::from_grid(
+        UVec2::splat(TILE_SIZE as u32),
+        4,
+        1,
+        None,
+        None,
+    ));
+    let npc_image = asset_server.load("tuxdoge.png");
+
+    let (width, height) = level.size();
+    for row in 0..height {
+        for col in 0..width {
+            let position = level.tile_position(col, row).extend(0.);
+            match level.tile(col, row) {
+                TopDownTile::Floor => {}
+                TopDownTile::Wall(index) => {
+                    commands.spawn((
+                        Sprite::from_atlas_image(
+                            brick_sheet.clone(),
+                            TextureAtlas {
+                                layout: brick_layout.clone(),
+                                index,
+                            },
+                        ),
+                        Transform::from_translation(position),
+                        Solid {
+                            offset: Vec2::ZERO,
+                            half_size: Vec2::splat(TILE_SIZE / 2.),
+                        },
+                        YSort {
+                            feet: TILE_SIZE / 2.,
+                        },
+                    ));
+                }
+                // Only the base is solid so the top can be walked behind
+                TopDownTile::Npc => {
+                    commands.spawn((
+                        Sprite {
+                            image: npc_image.clone(),
+                            custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE * 0.75)),
+                            ..default()
+                        },
+                        Transform::from_translation(position),
+                        Solid {
+                            offset: Vec2::new(0., -TILE_SIZE * 0.25),
+                            half_size: Vec2::new(TILE_SIZE * 0.4, TILE_SIZE * 0.125),
+                        },
+                        YSort {
+                            feet: TILE_SIZE * 0.375,
+                        },
+                    ));
+                }
+                TopDownTile::Object => {
+                    commands.spawn((
+                        Sprite::from_color(
+                            Color::srgb(0.55, 0.4, 0.25),
+                            Vec2::new(TILE_SIZE * 0.8, TILE_SIZE),
+                        ),
+                        Transform::from_translation(position),
+                        Solid {
+                            offset: Vec2::new(0., -TILE_SIZE * 0.35),
+                            half_size: Vec2::new(TILE_SIZE * 0.4, TILE_SIZE * 0.15),
+                        },
+                        YSort {
+                            feet: TILE_SIZE / 2.,
+                        },
+                    ));
+                }
+                // The rest are walked onto rather than around, coloured like
+                // the side-scroller's editor shows them
+                TopDownTile::Checkpoint => {
+                    commands.spawn((
+                        Sprite::from_color(
+                            Color::srgb(0.2, 0.8, 0.3),
+                            Vec2::new(TILE_SIZE * 0.2, TILE_SIZE * 0.8),
+                        ),
+                        Transform::from_translation(position),
+                        YSort {
+                            feet: TILE_SIZE * 0.4,
+                        },
+                        Touch::Checkpoint,
+                    ));
+                }
+                TopDownTile::Hazard => {
+                    commands.spawn((
+                        Sprite::from_color(
+                            Color::srgb(0.85, 0.15, 0.15),
+                            Vec2::splat(TILE_SIZE * 0.6),
+                        ),
+                        Transform::from_translation(position),
+                        YSort { feet: 0. },
+                        Touch::Hurt,
+                    ));
+                }
+                TopDownTile::Collectible => {
+                    commands.spawn((
+                        Sprite::from_color(
+                            Color::srgb(1., 0.85, 0.1),
+                            Vec2::splat(TILE_SIZE * 0.3),
+                        ),
+                        Transform::from_translation(position),
+                        YSort { feet: 0. },
+                        Touch::Collect,
+                    ));
+                }
+                TopDownTile::Enemy => {
+                    commands.spawn((
+                        Sprite::from_color(
+                            Color::srgb(0.6, 0.2, 0.8),
+                            Vec2::splat(TILE_SIZE * 0.6),
+                        ),
+                        Transform::from_translation(position),
+                        YSort {
+                            feet: TILE_SIZE * 0.3,
+                        },
+                        Touch::Hurt,
+                    ));
+                }
+            }
+        }
+    }
+
+    let walker_sheet = asset_server.load("walker.png");
+    let walker_layout = texture_atlases.add(TextureAtlasLayout::from_grid(
+        UVec2::splat(TILE_SIZE as u32),
+        WALKER_FRAMES as u32,
+        WALKER_FACINGS as u32,
+        None,
+        None,
+    ));
+    let walker = Walker::default();
+    let (col, row) = level.start().unwrap_or((0, 0));
+    let start = level.tile_position(col, row);
+    commands.spawn((
+        Sprite::from_atlas_image(
+            walker_sheet,
+            TextureAtlas {
+                layout: walker_layout,
+                index: walker.facing * WALKER_FRAMES,
+            },
+        ),
+        Transform::from_translation(start.extend(0.)),
+        Solid {
+            offset: Vec2::new(0., -TILE_SIZE * 0.2),
+            half_size: Vec2::new(TILE_SIZE * 0.25, TILE_SIZE * 0.1),
+        },
+        YSort {
+            feet: TILE_SIZE * 0.3,
+        },
+        walker,
+        Velocity::default(),
+        PLAYER_STATS,
+        TopDownPlayer,
+    ));
+
+    commands.insert_resource(Respawn(start));
+    commands.insert_resource(LevelBounds(level.bounds()));
+}
+
+/// Pushes `rect` back out of any solid it was moved into along one axis
+fn resolve(rect: Rect, moved: Vec2, solids: &[Rect]) -> Vec2 {
+    let mut push = Vec2::ZERO;
+    for solid in solids {
+        let overlap = rect.intersect(*solid);
+        if overlap.is_empty() {
+            continue;
+        }
+        if moved.x > 0. {
+            push.x = push.x.min(-overlap.width());
+        } else if moved.x < 0. {
+            push.x = push.x.max(overlap.width());
+        }
+        if moved.y > 0. {
+            push.y = push.y.min(-overlap.height());
+        } else if moved.y < 0. {
+            push.y = push.y.max(overlap.height());
+        }
+    }
+    push
+}
+
+fn move_player(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    bounds: Res<LevelBounds>,
+    player: Single<(&mut Transform, &mut Velocity, &MovementStats, &Solid), With<TopDownPlayer>>,
+    solids: Query<(&Transform, &Solid), Without<TopDownPlayer>>,
+) {
+    let (mut transform, mut velocity, stats, body) = player.into_inner();
+
+    let mut dir = Vec2::ZERO;
+    if input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
+        dir.x -= 1.;
+    }
+    if input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
+        dir.x += 1.;
+    }
+    if input.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) {
+        dir.y += 1.;
+    }
+    if input.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) {
+        dir.y -= 1.;
+    }
+
+    let deltat = time.delta_secs();
+    **velocity = stats.step(**velocity, dir, false, deltat);
+
+    let solids: Vec<Rect> = solids
+        .iter()
+        .map(|(t, solid)| solid.rect(t.translation.truncate()))
+        .collect();
+
+    // One axis at a time, so sliding along a wall still works
+    let mut position = transform.translation.truncate();
+    for step in [
+        Vec2::new(velocity.x * deltat, 0.),
+        Vec2::new(0., velocity.y * deltat),
+    ] {
+        position += step;
+        let push = resolve(body.rect(position), step, &solids);
+        position += push;
+        if push.x != 0. {
+            velocity.x = 0.;
+        }
+        if push.y != 0. {
+            velocity.y = 0.;
+        }
+    }
+
+    // Keep the whole body inside the level
+    let min = bounds.min - body.offset + body.half_size;
+    let max = bounds.max - body.offset - body.half_size;
+    position = position.clamp(min, max.max(min));
+
+    transform.translation = position.extend(transform.translation.z);
+}
+
+/// Things the player walks onto, checked against the player's feet
+fn touch_things(
+    mut commands: Commands,
+    mut respawn: ResMut<Respawn>,
+    mut collected: ResMut<Collected>,
+    player: Single<(&mut Transform, &mut Velocity, &Solid), With<TopDownPlayer>>,
+    things: Query<(Entity, &Transform, &Touch), Without<TopDownPlayer>>,
+) {
+    let (mut transform, mut velocity, body) = player.into_inner();
+    let feet = body.rect(transform.translation.truncate());
+
+    for (entity, thing, touch) in &things {
+        let area =
+            Rect::from_center_half_size(thing.translation.truncate(), Vec2::splat(TILE_SIZE * 0.3));
+        if feet.intersect(area).is_empty() {
+            continue;
+        }
+        match touch {
+            Touch::Checkpoint => **respawn = thing.translation.truncate(),
+            Touch::Collect => {
+                commands.entity(entity).despawn();
+                **collected += 1;
+                info!("Collected {}", **collected);
+            }
+            Touch::Hurt => {
+                transform.translation = respawn.extend(transform.translation.z);
+                **velocity = Vec2::ZERO;
+                return;
+            }
+        }
+    }
+}
+
+fn animate_walkers(time: Res<Time>, mut walkers: Query<(&mut Sprite, &mut Walker, &Velocity)>) {
+    for (mut sprite, mut walker, velocity) in &mut walkers {
+        match facing_row(**velocity) {
+            Some(facing) => {
+                walker.facing = facing;
+                walker.timer.tick(time.delta());
+                if walker.timer.just_finished() {
+                    walker.frame = (walker.frame + 1) % WALKER_FRAMES;
+                }
+            }
+            None => {
+                walker.frame = 0;
+                walker.timer.reset();
+            }
+        }
+
+        if let Some(atlas) = &mut sprite.texture_atlas {
+            atlas.index = walker.facing * WALKER_FRAMES + walker.frame;
+        }
+    }
+}
+
+fn follow_player(
+    bounds: Res<LevelBounds>,
+    player: Single<&Transform, With<TopDownPlayer>>,
+    mut camera: Single<&mut Transform, (With<Camera>, Without<TopDownPlayer>)>,
+    window: Single<&Window>,
+) {
+    // A level smaller than the window just stays centred
+    let max = (bounds.half_size() - window.size() / 2.).max(Vec2::ZERO);
+    let target = player
+        .translation
+        .truncate()
+        .clamp(bounds.center() - max, bounds.center() + max);
+    camera.translation = target.extend(camera.translation.z);
+}
+
+fn y_sort(mut sorted: Query<(&mut Transform, &YSort)>) {
+    for (mut transform, y_sort) in &mut sorted {
+        let feet = transform.translation.y - y_sort.feet;
+        transform.translation.z = Y_SORT_BASE - feet * Y_SORT_SCALE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written the way bevy_project_structure's levels are, extra fields and all
+    const SIDE_SCROLLER_LEVEL: &str = r#"(
+    name: "First Steps",
+    npc_dialogue: ["dialogue/guide.dialogue.ron"],
+    triggers: [
+        (at: (9.0, 3.0), shape: Box(1.0, 5.0), action: Goal),
+    ],
+    tiles: [
+        "..........",
+        "....*.....",
+        "...0123...",
+        ".N..C.^.E?",
+        "0123012301",
+    ],
+)"#;
+
+    #[test]
+    fn reads_side_scroller_levels() {
+        let level: TopDownLevel = ron::de::from_str(SIDE_SCROLLER_LEVEL).unwrap();
+        assert_eq!(level.name, "First Steps");
+        assert_eq!(level.size(), (10, 5));
+        assert_eq!(level.tile(0, 4), TopDownTile::Wall(0));
+        assert_eq!(level.tile(4, 1), TopDownTile::Collectible);
+        assert_eq!(level.tile(1, 3), TopDownTile::Npc);
+        assert_eq!(level.tile(4, 3), TopDownTile::Checkpoint);
+        assert_eq!(level.tile(6, 3), TopDownTile::Hazard);
+        assert_eq!(level.tile(8, 3), TopDownTile::Enemy);
+        // Unknown characters are empty in the side-scroller, and floor here
+        assert_eq!(level.tile(9, 3), TopDownTile::Floor);
+        assert_eq!(level.tile(0, 0), TopDownTile::Floor);
+        // The bottom row is all wall, so the player starts just above it
+        assert_eq!(level.start(), Some((0, 3)));
+    }
+
+    #[test]
+    fn courtyard_is_walled_in() {
+        let level: TopDownLevel =
+            ron::de::from_str(include_str!("../assets/levels/courtyard.level.ron")).unwrap();
+        let (width, height) = level.size();
+        for col in 0..width {
+            assert!(matches!(level.tile(col, 0), TopDownTile::Wall(_)));
+            assert!(matches!(level.tile(col, height - 1), TopDownTile::Wall(_)));
+        }
+        assert_eq!(level.tile(3, 2), TopDownTile::Object);
+        assert_eq!(level.start(), Some((1, 11)));
+    }
+
+    #[test]
+    fn tiles_are_centred_on_the_origin() {
+        let level = TopDownLevel {
+            name: "test".into(),
+            tiles: vec!["0..".into(), "..1".into()],
+        };
+        assert_eq!(level.tile_position(0, 0), Vec2::new(-100., 50.));
+        assert_eq!(level.tile_position(2, 1), Vec2::new(100., -50.));
+        assert_eq!(level.bounds(), Rect::new(-150., -100., 150., 100.));
+        assert_eq!(level.tile(5, 0), TopDownTile::Floor);
+    }
+
+    #[test]
+    fn facing_rows_go_counterclockwise_from_east() {
+        assert_eq!(facing_row(Vec2::ZERO), None);
+        assert_eq!(facing_row(Vec2::X), Some(0));
+        assert_eq!(facing_row(Vec2::new(1., 1.)), Some(1));
+        assert_eq!(facing_row(Vec2::Y), Some(2));
+        assert_eq!(facing_row(Vec2::NEG_X), Some(4));
+        assert_eq!(facing_row(Vec2::NEG_Y), Some(6));
+        assert_eq!(facing_row(Vec2::new(1., -1.)), Some(7));
+        // Mostly east with a little drift still faces east
+        assert_eq!(facing_row(Vec2::new(10., -1.)), Some(0));
+    }
+
+    #[test]
+    fn walking_into_a_wall_pushes_back_out() {
+        let wall = Rect::new(100., -50., 200., 50.);
+        let body = Rect::new(80., -10., 120., 10.);
+        assert_eq!(
+            resolve(body, Vec2::new(5., 0.), &[wall]),
+            Vec2::new(-20., 0.)
+        );
+        let clear = Rect::new(0., -10., 40., 10.);
+        assert_eq!(resolve(clear, Vec2::new(5., 0.), &[wall]), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,240 @@
+//! Turns telemetry sessions into a picture of one level
+//!
+//! ```text
+//! cargo run --bin heatmap -- assets/levels/level1.level.ron session_*.jsonl -o heatmap.png
+//! ```
+//!
+//! The PNG has one pixel per world unit, with the level's tiles drawn faintly
+//! underneath. Blue to yellow shows where players spent their time, and red
+//! shows where they died. Only records for the level's name are counted.
+
+use serde::Deserialize;
+use std::{error::Error, fs, io::BufWriter, path::PathBuf};
+
+/// Must match the game's `TILE_SIZE`
+const TILE_SIZE: usize = 100;
+/// How far in world units one position sample or death spreads
+const TIME_RADIUS: f32 = TILE_SIZE as f32 * 0.5;
+const DEATH_RADIUS: f32 = TILE_SIZE as f32 * 0.35;
+
+/// The parts of a level file this needs, everything else is ignored
+#[derive(Deserialize)]
+struct Level {
+    name: String,
+    tiles: Vec<String>,
+}
+
+/// The parts of a telemetry record this needs
+#[derive(Deserialize)]
+struct Record {
+    event: String,
+    level: Option<String>,
+    x: Option<f32>,
+    y: Option<f32>,
+}
+
+struct Args {
+    level: PathBuf,
+    sessions: Vec<PathBuf>,
+    output: PathBuf,
+}
+
+const USAGE: &str = "usage: heatmap <level.ron> <session.jsonl>... [-o <output.png>]";
+
+fn parse_args() -> Result<Args, String> {
+    let mut level = None;
+    let mut sessions = Vec::new();
+    let mut output = PathBuf::from("heatmap.png");
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = args.next().ok_or(USAGE)?.into(),
+            "-h" | "--help" => return Err(USAGE.into()),
+            _ if level.is_none() => level = Some(arg.into()),
+            _ => sessions.push(arg.into()),
+        }
+    }
+
+    match level {
+        Some(level) if !sessions.is_empty() => Ok(Args {
+            level,
+            sessions,
+            output,
+        }),
+        _ => Err(USAGE.into()),
+    }
+}
+
+/// Accumulated weights, one per pixel, with the origin at the bottom left
+struct Layer {
+    width: usize,
+    height: usize,
+    weights: Vec<f32>,
+}
+
+impl Layer {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            weights: vec![0.; width * height],
+        }
+    }
+
+    /// Adds a soft round blob centered on `(x, y)`
+    fn splat(&mut self, x: f32, y: f32, radius: f32) {
+        let min_x = (x - radius).floor().max(0.) as usize;
+        let min_y = (y - radius).floor().max(0.) as usize;
+        let max_x = ((x + radius).ceil() as usize).min(self.width);
+        let max_y = ((y + radius).ceil() as usize).min(self.height);
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let dx = px as f32 + 0.5 - x;
+                let dy = py as f32 + 0.5 - y;
+                let falloff = 1. - (dx * dx + dy * dy) / (radius * radius);
+                if falloff > 0. {
+                    self.weights[py * self.width + px] += falloff * falloff;
+                }
+            }
+        }
+    }
+
+    /// Weight of a pixel from 0 to 1, relative to the heaviest one
+    fn normalized(&self) -> impl Fn(usize, usize) -> f32 + '_ {
+        let max = self.weights.iter().copied().fold(0., f32::max);
+        move |px, py| {
+            if max > 0. {
+                self.weights[py * self.width + px] / max
+            } else {
+                0.
+            }
+        }
+    }
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(usage) => {
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(&args) {
+        eprintln!("heatmap: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let level: Level = ron::from_str(&fs::read_to_string(&args.level)?)?;
+    let cols = level.tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+    let rows = level.tiles.len();
+    if cols == 0 {
+        return Err(format!("{} has no tiles", args.level.display()).into());
+    }
+
+    let (width, height) = (cols * TILE_SIZE, rows * TILE_SIZE);
+    let mut time = Layer::new(width, height);
+    let mut deaths = Layer::new(width, height);
+    let (mut samples, mut died) = (0, 0);
+
+    for session in &args.sessions {
+        let text = fs::read_to_string(session)
+            .map_err(|e| format!("could not read {}: {}", session.display(), e))?;
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Record = serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {}", session.display(), number + 1, e))?;
+            if record.level.as_deref() != Some(level.name.as_str()) {
+                continue;
+            }
+            let (Some(x), Some(y)) = (record.x, record.y) else {
+                continue;
+            };
+            match record.event.as_str() {
+                "position" => {
+                    time.splat(x, y, TIME_RADIUS);
+                    samples += 1;
+                }
+                "death" => {
+                    deaths.splat(x, y, DEATH_RADIUS);
+                    died += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let pixels = render(&level, &time, &deaths);
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(fs::File::create(&args.output)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+
+    println!(
+        "{}: {} position samples and {} deaths on \"{}\" from {} session(s)",
+        args.output.display(),
+        samples,
+        died,
+        level.name,
+        args.sessions.len()
+    );
+    Ok(())
+}
+
+/// RGB rows from the top of the level down, as PNG expects
+fn render(level: &Level, time: &Layer, deaths: &Layer) -> Vec<u8> {
+    let (width, height) = (time.width, time.height);
+    let time = time.normalized();
+    let deaths = deaths.normalized();
+    let mut pixels = Vec::with_capacity(width * height * 3);
+
+    for py in (0..height).rev() {
+        // Level rows are listed top to bottom
+        let row = level.tiles.get(level.tiles.len() - 1 - py / TILE_SIZE);
+        for px in 0..width {
+            let tile = row.and_then(|row| row.as_bytes().get(px / TILE_SIZE).copied());
+            let mut color = match tile {
+                Some(b'0'..=b'3') => [0.35, 0.35, 0.38],
+                Some(b'^') => [0.35, 0.12, 0.12],
+                _ => [0.1, 0.1, 0.12],
+            };
+
+            // Square root so short visits still show up next to long ones
+            let heat = time(px, py).sqrt();
+            if heat > 0. {
+                color = mix(color, ramp(heat), (heat * 1.5).min(0.85));
+            }
+            let death = deaths(px, py).sqrt();
+            if death > 0. {
+                color = mix(color, [1., 0.1, 0.1], death.min(0.9));
+            }
+
+            pixels.extend(color.map(|channel| (channel * 255.).round() as u8));
+        }
+    }
+    pixels
+}
+
+/// Blue through green to yellow
+fn ramp(t: f32) -> [f32; 3] {
+    if t < 0.5 {
+        mix([0.1, 0.2, 0.9], [0.1, 0.8, 0.4], t * 2.)
+    } else {
+        mix([0.1, 0.8, 0.4], [1., 0.9, 0.1], t * 2. - 1.)
+    }
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
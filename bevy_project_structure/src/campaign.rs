@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use crate::{GameState, player::PlayerDied};
+
+pub enum LevelSource {
+    /// Path to a level file, relative to the assets folder
+    File(&'static str),
+    /// Built by `levelgen::generate` from this seed
+    Generated(u64),
+}
+
+impl LevelSource {
+    /// File the level editor saves this level to, relative to the assets folder
+    pub fn save_path(&self) -> String {
+        match self {
+            LevelSource::File(path) => path.to_string(),
+            LevelSource::Generated(seed) => format!("levels/generated_{}.level.ron", seed),
+        }
+    }
+}
+
+/// Levels in the order they are played
+pub const CAMPAIGN: [LevelSource; 4] = [
+    LevelSource::File("levels/level1.level.ron"),
+    LevelSource::File("levels/level2.level.ron"),
+    LevelSource::Generated(1666),
+    LevelSource::File("levels/level3.level.ron"),
+];
+
+/// Index into `CAMPAIGN` of the level being played
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct CurrentLevel(pub usize);
+
+impl CurrentLevel {
+    pub fn source(&self) -> &'static LevelSource {
+        &CAMPAIGN[self.0]
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.0 + 1 >= CAMPAIGN.len()
+    }
+}
+
+/// Lives at the start of a game, and again after running out
+pub const STARTING_LIVES: u32 = 3;
+
+/// Everything the player has earned so far, kept from one level to the next
+#[derive(Resource)]
+pub struct Progress {
+    pub levels_cleared: usize,
+    pub score: u32,
+    pub play_time: f32,
+    pub lives: u32,
+    /// How many levels from the start of `CAMPAIGN` have been reached
+    pub unlocked_levels: usize,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            levels_cleared: 0,
+            score: 0,
+            play_time: 0.,
+            lives: STARTING_LIVES,
+            unlocked_levels: 1,
+        }
+    }
+}
+
+pub struct CampaignPlugin;
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>()
+            .init_resource::<Progress>()
+            .add_systems(
+                Update,
+                (track_play_time, lose_life).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn track_play_time(time: Res<Time>, mut progress: ResMut<Progress>) {
+    progress.play_time += time.delta_secs();
+}
+
+/// Running out of lives starts the level over from the beginning
+fn lose_life(
+    mut died: EventReader<PlayerDied>,
+    mut progress: ResMut<Progress>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if died.is_empty() {
+        return;
+    }
+    died.clear();
+
+    progress.lives = progress.lives.saturating_sub(1);
+    if progress.lives == 0 {
+        info!("Out of lives, restarting the level");
+        progress.lives = STARTING_LIVES;
+        next_state.set(GameState::Loading);
+    } else {
+        info!("{} lives left", progress.lives);
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+use crate::{
+    GameState, TILE_SIZE,
+    collision::{Collider, overlaps},
+    loading::despawn_with,
+    player::Player,
+};
+
+const INACTIVE_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+const ACTIVE_COLOR: Color = Color::srgb(0.2, 0.8, 0.3);
+const ACTIVATE_TIME: f32 = 0.6;
+
+#[derive(Component)]
+pub struct Checkpoint {
+    active: bool,
+}
+
+/// Plays once when a checkpoint is first touched
+#[derive(Component, Deref, DerefMut)]
+struct ActivateAnimation(Timer);
+
+/// Sent the first time each checkpoint is touched
+#[derive(Event)]
+pub struct CheckpointReached {
+    pub position: Vec2,
+}
+
+/// Where the player comes back after dying, if any checkpoint was reached
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ActiveCheckpoint(Option<Vec2>);
+
+pub struct CheckpointPlugin;
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveCheckpoint>()
+            .add_event::<CheckpointReached>()
+            .add_systems(OnEnter(GameState::Loading), reset_checkpoint)
+            .add_systems(
+                Update,
+                (activate_checkpoints, animate_checkpoints).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_with::<Checkpoint>);
+    }
+}
+
+pub fn spawn_checkpoint(commands: &mut Commands, position: Vec2) {
+    commands.spawn((
+        Sprite::from_color(INACTIVE_COLOR, Vec2::new(TILE_SIZE / 5., TILE_SIZE)),
+        Transform::from_translation(position.extend(2.)),
+        Collider(Vec2::new(TILE_SIZE / 10., TILE_SIZE / 2.)),
+        Checkpoint { active: false },
+    ));
+}
+
+fn reset_checkpoint(mut active_checkpoint: ResMut<ActiveCheckpoint>) {
+    **active_checkpoint = None;
+}
+
+fn activate_checkpoints(
+    mut commands: Commands,
+    player: Single<(&Transform, &Collider), With<Player>>,
+    mut checkpoints: Query<
+        (Entity, &Transform, &Collider, &mut Checkpoint, &mut Sprite),
+        Without<Player>,
+    >,
+    mut active_checkpoint: ResMut<ActiveCheckpoint>,
+    mut reached: EventWriter<CheckpointReached>,
+) {
+    let (player_transform, player_collider) = player.into_inner();
+    let player_box = player_collider.bounds(player_transform);
+
+    for (entity, transform, collider, mut checkpoint, mut sprite) in checkpoints.iter_mut() {
+        if checkpoint.active {
+            continue;
+        }
+
+        if overlaps(&player_box, &collider.bounds(transform)) {
+            let position = transform.translation.truncate();
+            info!("Checkpoint reached at {}", position);
+            checkpoint.active = true;
+            sprite.color = ACTIVE_COLOR;
+            **active_checkpoint = Some(position);
+            reached.write(CheckpointReached { position });
+            commands
+                .entity(entity)
+                .insert(ActivateAnimation(Timer::from_seconds(
+                    ACTIVATE_TIME,
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
+
+fn animate_checkpoints(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut checkpoints: Query<(Entity, &mut Transform, &mut ActivateAnimation)>,
+) {
+    for (entity, mut transform, mut animation) in checkpoints.iter_mut() {
+        animation.tick(time.delta());
+
+        // Swell out and back once
+        transform.scale.x = 1. + (animation.fraction() * PI).sin() * 1.5;
+
+        if animation.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<ActivateAnimation>();
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState, TILE_SIZE,
+    campaign::Progress,
+    collision::{Collider, overlaps},
+    loading::despawn_with,
+    player::Player,
+};
+
+const COLLECTIBLE_COLOR: Color = Color::srgb(1., 0.85, 0.1);
+
+/// Picked up on touch for a point
+#[derive(Component)]
+pub struct Collectible;
+
+/// Sent when the player picks up a collectible at `position`
+#[derive(Event)]
+pub struct Collected {
+    pub position: Vec2,
+}
+
+pub struct CollectiblePlugin;
+impl Plugin for CollectiblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Collected>()
+            .add_systems(Update, collect.run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), despawn_with::<Collectible>);
+    }
+}
+
+pub fn spawn_collectible(commands: &mut Commands, position: Vec2) {
+    let size = Vec2::splat(TILE_SIZE / 3.);
+    commands.spawn((
+        Sprite::from_color(COLLECTIBLE_COLOR, size),
+        Transform::from_translation(position.extend(2.)),
+        Collider(size / 2.),
+        Collectible,
+    ));
+}
+
+fn collect(
+    mut commands: Commands,
+    player: Single<(&Transform, &Collider), With<Player>>,
+    collectibles: Query<(Entity, &Transform, &Collider), (With<Collectible>, Without<Player>)>,
+    mut progress: ResMut<Progress>,
+    mut collected: EventWriter<Collected>,
+) {
+    let (player_transform, player_collider) = player.into_inner();
+    let player_box = player_collider.bounds(player_transform);
+
+    for (entity, transform, collider) in &collectibles {
+        if overlaps(&player_box, &collider.bounds(transform)) {
+            progress.score += 1;
+            collected.write(Collected {
+                position: transform.translation.truncate(),
+            });
+            commands.entity(entity).despawn();
+        }
+    }
+}
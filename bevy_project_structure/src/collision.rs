@@ -0,0 +1,21 @@
+use bevy::{math::bounding::Aabb2d, prelude::*};
+
+/// Axis-aligned box centered on the entity, stored as half its size
+#[derive(Component, Clone, Copy, Deref)]
+pub struct Collider(pub Vec2);
+
+impl Collider {
+    pub fn bounds(&self, transform: &Transform) -> Aabb2d {
+        Aabb2d::new(transform.translation.truncate(), self.0)
+    }
+}
+
+/// Colliders the player stands on and cannot move through
+#[derive(Component)]
+pub struct Solid;
+
+/// Like `IntersectsVolume`, but boxes that only share an edge don't count,
+/// so standing on a brick is not an overlap
+pub fn overlaps(a: &Aabb2d, b: &Aabb2d) -> bool {
+    a.min.x < b.max.x && a.max.x > b.min.x && a.min.y < b.max.y && a.max.y > b.min.y
+}
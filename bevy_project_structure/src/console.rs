@@ -0,0 +1,277 @@
+use bevy::{
+    input::{
+        InputSystem,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use std::collections::HashMap;
+
+const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+const LOG_LINES: usize = 12;
+
+/// Runs a console command, given the words typed after its name
+///
+/// `Ok` and `Err` messages are both printed to the console.
+pub type ConsoleCommandFn = fn(&mut World, &[&str]) -> Result<String, String>;
+
+struct ConsoleCommand {
+    usage: &'static str,
+    run: ConsoleCommandFn,
+}
+
+/// Every command the console knows, keyed by name
+///
+/// Names may be more than one word (`spawn enemy`), and the longest name that
+/// matches the start of a line wins.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(HashMap<&'static str, ConsoleCommand>);
+
+/// Lets each plugin add its own console commands while it builds
+pub trait ConsoleExt {
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        run: ConsoleCommandFn,
+    ) -> &mut Self;
+}
+
+impl ConsoleExt for App {
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        run: ConsoleCommandFn,
+    ) -> &mut Self {
+        self.init_resource::<ConsoleCommands>();
+        self.world_mut()
+            .resource_mut::<ConsoleCommands>()
+            .0
+            .insert(name, ConsoleCommand { usage, run });
+        self
+    }
+}
+
+/// Systems that read the keyboard for the console, and hide it from
+/// everything else while the console is open
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConsoleSystems;
+
+#[derive(Resource, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    log: Vec<String>,
+    history: Vec<String>,
+    /// How far back through `history` the up arrow has gone
+    history_index: usize,
+    /// Line entered this frame, waiting for `run_submitted`
+    submitted: Option<String>,
+}
+
+#[derive(Component)]
+struct ConsoleRoot;
+
+#[derive(Component)]
+struct ConsoleLog;
+
+#[derive(Component)]
+struct ConsoleInput;
+
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .init_resource::<ConsoleCommands>()
+            .add_systems(Startup, setup_console)
+            // Read typing before anything else sees the keyboard, then hide it
+            .add_systems(
+                PreUpdate,
+                (toggle_console, read_input)
+                    .chain()
+                    .in_set(ConsoleSystems)
+                    .after(InputSystem),
+            )
+            .add_systems(Update, (run_submitted, update_console).chain())
+            .add_console_command("help", "help - list commands", help)
+            .add_console_command("clear", "clear - empty the console", clear);
+    }
+}
+
+fn setup_console(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.),
+                left: Val::Px(0.),
+                width: Val::Percent(100.),
+                height: Val::Percent(40.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::FlexEnd,
+                padding: UiRect::all(Val::Px(8.)),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.8)),
+            Visibility::Hidden,
+            GlobalZIndex(50),
+            ConsoleRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+                ConsoleLog,
+            ));
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+                TextColor(Color::srgb(1., 1., 0.6)),
+                ConsoleInput,
+            ));
+        });
+}
+
+fn toggle_console(
+    mut input: ResMut<ButtonInput<KeyCode>>,
+    mut console: ResMut<Console>,
+    mut root: Single<&mut Visibility, With<ConsoleRoot>>,
+) {
+    let close = console.open && input.just_pressed(KeyCode::Escape);
+    if input.just_pressed(TOGGLE_KEY) || close {
+        console.open = !console.open;
+        **root = if console.open {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        // Don't let the same key press do anything else
+        input.reset_all();
+    }
+}
+
+fn read_input(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut console: ResMut<Console>,
+) {
+    if !console.open {
+        keyboard_events.clear();
+        return;
+    }
+
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() || event.key_code == TOGGLE_KEY {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Enter => {
+                let line = std::mem::take(&mut console.input);
+                if !line.trim().is_empty() {
+                    console.history.push(line.clone());
+                    console.submitted = Some(line);
+                }
+                console.history_index = 0;
+            }
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::ArrowUp if console.history_index < console.history.len() => {
+                console.history_index += 1;
+                let index = console.history.len() - console.history_index;
+                console.input = console.history[index].clone();
+            }
+            Key::Space => console.input.push(' '),
+            Key::Character(text) => console.input.push_str(text),
+            _ => {}
+        }
+    }
+
+    // Keep the game from reacting to what is typed
+    keys.reset_all();
+}
+
+fn run_submitted(world: &mut World) {
+    let Some(line) = world.resource_mut::<Console>().submitted.take() else {
+        return;
+    };
+    world
+        .resource_mut::<Console>()
+        .log
+        .push(format!("> {}", line));
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    // Longest registered name matching the start of the line
+    let command = world
+        .resource::<ConsoleCommands>()
+        .0
+        .iter()
+        .filter(|(name, _)| {
+            let name_words: Vec<&str> = name.split_whitespace().collect();
+            words.starts_with(&name_words)
+        })
+        .max_by_key(|(name, _)| name.split_whitespace().count())
+        .map(|(name, command)| (name.split_whitespace().count(), command.run));
+
+    let output = match command {
+        Some((name_len, run)) => match run(world, &words[name_len..]) {
+            Ok(message) => message,
+            Err(message) => format!("error: {}", message),
+        },
+        None => format!("unknown command '{}', try 'help'", line.trim()),
+    };
+
+    world
+        .resource_mut::<Console>()
+        .log
+        .extend(output.lines().map(String::from));
+}
+
+fn update_console(
+    mut console: ResMut<Console>,
+    mut log: Single<&mut Text, (With<ConsoleLog>, Without<ConsoleInput>)>,
+    mut input: Single<&mut Text, (With<ConsoleInput>, Without<ConsoleLog>)>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+
+    let overflow = console.log.len().saturating_sub(LOG_LINES);
+    console.log.drain(..overflow);
+
+    log.0 = console.log.join("\n");
+    input.0 = format!("> {}_", console.input);
+}
+
+fn help(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let mut usages: Vec<&str> = world
+        .resource::<ConsoleCommands>()
+        .0
+        .values()
+        .map(|command| command.usage)
+        .collect();
+    usages.sort();
+    Ok(usages.join("\n"))
+}
+
+fn clear(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    world.resource_mut::<Console>().log.clear();
+    Ok(String::new())
+}
+
+/// Parse one argument, with a readable error for the console
+pub fn parse_arg<T: std::str::FromStr>(args: &[&str], index: usize) -> Result<T, String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("missing argument {}", index + 1))?;
+    arg.parse()
+        .map_err(|_| format!("could not understand '{}'", arg))
+}
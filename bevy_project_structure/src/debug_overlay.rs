@@ -0,0 +1,160 @@
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    GameState,
+    collision::Collider,
+    level::{Background, Brick, LevelLength},
+    player::{Player, Velocity},
+    trigger::{Trigger, draw_trigger_shape},
+    viewport::VirtualViewport,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+#[derive(Component)]
+struct DebugText;
+
+#[derive(Resource, Default, Deref, DerefMut)]
+struct OverlayVisible(bool);
+
+pub struct DebugOverlayPlugin;
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FrameTimeDiagnosticsPlugin::default())
+            .init_resource::<OverlayVisible>()
+            .add_systems(Startup, setup_overlay)
+            .add_systems(Update, toggle_overlay)
+            .add_systems(
+                Update,
+                (
+                    update_overlay,
+                    draw_colliders,
+                    draw_triggers,
+                    draw_camera_bounds,
+                )
+                    .run_if(|visible: Res<OverlayVisible>| **visible),
+            );
+    }
+}
+
+fn setup_overlay(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 16.,
+            ..default()
+        },
+        TextColor(Color::srgb(0.4, 1., 0.4)),
+        BackgroundColor(Color::BLACK.with_alpha(0.6)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            right: Val::Px(10.),
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        Visibility::Hidden,
+        // Draw over any other UI
+        GlobalZIndex(100),
+        DebugText,
+    ));
+}
+
+fn toggle_overlay(
+    input: Res<ButtonInput<KeyCode>>,
+    mut visible: ResMut<OverlayVisible>,
+    mut text: Single<&mut Visibility, With<DebugText>>,
+) {
+    if input.just_pressed(TOGGLE_KEY) {
+        **visible = !**visible;
+        **text = if **visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    state: Res<State<GameState>>,
+    bricks: Query<(), With<Brick>>,
+    backgrounds: Query<(), With<Background>>,
+    players: Query<(), With<Player>>,
+    velocity: Option<Single<&Velocity, With<Player>>>,
+    mut text: Single<&mut Text, With<DebugText>>,
+) {
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(0.);
+    let frame_time = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(|frame_time| frame_time.smoothed())
+        .unwrap_or(0.);
+    let velocity = velocity.map_or(String::from("-"), |v| format!("{:.0}", ***v));
+
+    text.0 = format!(
+        "FPS: {:.0} ({:.2} ms)\n\
+         State: {:?}\n\
+         Bricks: {}\n\
+         Backgrounds: {}\n\
+         Players: {}\n\
+         Velocity: {}",
+        fps,
+        frame_time,
+        state.get(),
+        bricks.iter().count(),
+        backgrounds.iter().count(),
+        players.iter().count(),
+        velocity,
+    );
+}
+
+fn draw_colliders(mut gizmos: Gizmos, colliders: Query<(&Transform, &Collider)>) {
+    for (transform, collider) in &colliders {
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            **collider * 2.,
+            Color::srgb(1., 0., 1.),
+        );
+    }
+}
+
+fn draw_triggers(mut gizmos: Gizmos, triggers: Query<(&Transform, &Trigger)>) {
+    for (transform, trigger) in &triggers {
+        draw_trigger_shape(
+            &mut gizmos,
+            transform.translation.truncate(),
+            trigger.shape,
+            Color::srgb(1., 0.5, 0.),
+        );
+    }
+}
+
+fn draw_camera_bounds(
+    mut gizmos: Gizmos,
+    level_length: Option<Res<LevelLength>>,
+    viewport: Res<VirtualViewport>,
+    camera: Single<&Transform, With<Camera>>,
+) {
+    // What the camera sees right now
+    gizmos.rect_2d(
+        camera.translation.truncate(),
+        viewport.size - 4.,
+        Color::srgb(1., 1., 0.),
+    );
+
+    // Everything move_camera can ever show of the level
+    if let Some(level_length) = level_length {
+        let size = Vec2::new(**level_length, viewport.height());
+        gizmos.rect_2d(
+            Vec2::new(viewport.min().x + size.x / 2., 0.),
+            size,
+            Color::srgb(0., 1., 1.),
+        );
+    }
+}
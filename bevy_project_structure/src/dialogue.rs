@@ -0,0 +1,418 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::{GameState, npc::Npc, player::Player};
+
+/// Letters revealed per second
+const TYPE_SPEED: f32 = 45.;
+const PORTRAIT_SIZE: f32 = 128.;
+const INTERACT_KEYS: [KeyCode; 2] = [KeyCode::KeyE, KeyCode::Enter];
+
+/// A conversation, stored as RON in `assets/dialogue/`
+///
+/// Starting from `start`, each node shows its text, then either offers its
+/// `choices` or moves on to `next`. A node with neither ends the dialogue, as
+/// does a choice without a `next`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct Dialogue {
+    pub speakers: HashMap<String, Speaker>,
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Speaker {
+    /// Image shown beside their lines, relative to the assets folder
+    #[serde(default)]
+    pub portrait: Option<String>,
+    /// Used for their name, and behind their portrait
+    pub color: (f32, f32, f32),
+    #[serde(skip)]
+    portrait_image: Option<Handle<Image>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DialogueNode {
+    pub speaker: String,
+    pub text: String,
+    #[serde(default)]
+    pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Choice {
+    pub text: String,
+    pub next: Option<String>,
+}
+
+impl Dialogue {
+    /// Catch typos in node and speaker names when the file loads, rather than
+    /// partway through a conversation
+    fn validate(&self) -> Result<(), String> {
+        let check_node = |id: &String| {
+            if self.nodes.contains_key(id) {
+                Ok(())
+            } else {
+                Err(format!("no node called '{}'", id))
+            }
+        };
+
+        check_node(&self.start)?;
+        for node in self.nodes.values() {
+            if !self.speakers.contains_key(&node.speaker) {
+                return Err(format!("no speaker called '{}'", node.speaker));
+            }
+            for next in node
+                .next
+                .iter()
+                .chain(node.choices.iter().flat_map(|c| &c.next))
+            {
+                check_node(next)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct DialogueLoader;
+
+impl AssetLoader for DialogueLoader {
+    type Asset = Dialogue;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut dialogue: Dialogue = ron::de::from_bytes(&bytes)?;
+        dialogue.validate()?;
+
+        for speaker in dialogue.speakers.values_mut() {
+            speaker.portrait_image = speaker
+                .portrait
+                .as_ref()
+                .map(|path| load_context.load(path));
+        }
+        Ok(dialogue)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue.ron"]
+    }
+}
+
+/// The conversation on screen, if any
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ActiveDialogue(Option<DialogueRun>);
+
+pub struct DialogueRun {
+    dialogue: Handle<Dialogue>,
+    node: String,
+    /// Letters of the node's text revealed so far
+    shown: f32,
+    selected: usize,
+}
+
+/// Run condition for gameplay that should wait while someone is talking
+pub fn dialogue_closed(active: Res<ActiveDialogue>) -> bool {
+    active.is_none()
+}
+
+#[derive(Component)]
+struct DialogueBox;
+
+#[derive(Component)]
+struct DialoguePortrait;
+
+#[derive(Component)]
+struct DialogueSpeaker;
+
+#[derive(Component)]
+struct DialogueText;
+
+#[derive(Component)]
+struct DialogueChoices;
+
+pub struct DialoguePlugin;
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Dialogue>()
+            .init_asset_loader::<DialogueLoader>()
+            .init_resource::<ActiveDialogue>()
+            .add_systems(Startup, setup_dialogue_box)
+            .add_systems(
+                Update,
+                (interact, select_choice, type_text, update_dialogue_box)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), close_dialogue);
+    }
+}
+
+fn setup_dialogue_box(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(20.),
+                left: Val::Percent(10.),
+                width: Val::Percent(80.),
+                min_height: Val::Px(PORTRAIT_SIZE + 24.),
+                padding: UiRect::all(Val::Px(12.)),
+                column_gap: Val::Px(16.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.85)),
+            BorderRadius::all(Val::Px(8.)),
+            Visibility::Hidden,
+            GlobalZIndex(30),
+            DialogueBox,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Px(PORTRAIT_SIZE),
+                    height: Val::Px(PORTRAIT_SIZE),
+                    flex_shrink: 0.,
+                    ..default()
+                },
+                ImageNode::default(),
+                BackgroundColor(Color::NONE),
+                DialoguePortrait,
+            ));
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(8.),
+                    flex_grow: 1.,
+                    ..default()
+                })
+                .with_children(|column| {
+                    column.spawn((
+                        Text::default(),
+                        TextFont {
+                            font_size: 22.,
+                            ..default()
+                        },
+                        DialogueSpeaker,
+                    ));
+                    column.spawn((
+                        Text::default(),
+                        TextFont {
+                            font_size: 20.,
+                            ..default()
+                        },
+                        DialogueText,
+                    ));
+                    column.spawn((
+                        Text::default(),
+                        TextFont {
+                            font_size: 18.,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(1., 1., 0.6)),
+                        DialogueChoices,
+                    ));
+                });
+        });
+}
+
+/// Talk to the nearest NPC in reach, or move the conversation along
+fn interact(
+    input: Res<ButtonInput<KeyCode>>,
+    dialogues: Res<Assets<Dialogue>>,
+    mut active: ResMut<ActiveDialogue>,
+    player: Single<&Transform, With<Player>>,
+    npcs: Query<(&Transform, &Npc)>,
+) {
+    if !input.any_just_pressed(INTERACT_KEYS) {
+        return;
+    }
+
+    let Some(run) = active.0.as_mut() else {
+        let player = player.translation.truncate();
+        let nearest = npcs
+            .iter()
+            .filter(|(transform, npc)| npc.in_reach(transform, player))
+            .min_by(|(a, _), (b, _)| {
+                let a = a.translation.truncate().distance(player);
+                let b = b.translation.truncate().distance(player);
+                a.total_cmp(&b)
+            });
+        if let Some((_, npc)) = nearest
+            && let Some(dialogue) = dialogues.get(&npc.dialogue)
+        {
+            **active = Some(DialogueRun {
+                dialogue: npc.dialogue.clone(),
+                node: dialogue.start.clone(),
+                shown: 0.,
+                selected: 0,
+            });
+        }
+        return;
+    };
+
+    let Some(dialogue) = dialogues.get(&run.dialogue) else {
+        **active = None;
+        return;
+    };
+    let node = &dialogue.nodes[&run.node];
+
+    // First press finishes the line, the next one moves on
+    let length = node.text.chars().count() as f32;
+    if run.shown < length {
+        run.shown = length;
+        return;
+    }
+
+    let next = match node.choices.get(run.selected) {
+        Some(choice) => choice.next.clone(),
+        None => node.next.clone(),
+    };
+    match next {
+        Some(next) => {
+            run.node = next;
+            run.shown = 0.;
+            run.selected = 0;
+        }
+        None => **active = None,
+    }
+}
+
+fn select_choice(
+    input: Res<ButtonInput<KeyCode>>,
+    dialogues: Res<Assets<Dialogue>>,
+    mut active: ResMut<ActiveDialogue>,
+) {
+    let Some(run) = active.0.as_mut() else {
+        return;
+    };
+    let Some(dialogue) = dialogues.get(&run.dialogue) else {
+        return;
+    };
+    let choices = dialogue.nodes[&run.node].choices.len();
+    if choices == 0 {
+        return;
+    }
+
+    if input.any_just_pressed([KeyCode::ArrowUp, KeyCode::KeyW]) {
+        run.selected = (run.selected + choices - 1) % choices;
+    }
+    if input.any_just_pressed([KeyCode::ArrowDown, KeyCode::KeyS]) {
+        run.selected = (run.selected + 1) % choices;
+    }
+}
+
+fn type_text(
+    time: Res<Time>,
+    dialogues: Res<Assets<Dialogue>>,
+    mut active: ResMut<ActiveDialogue>,
+) {
+    // Only touch the resource while typing, so the box isn't redrawn every frame
+    let Some(length) = active.0.as_ref().and_then(|run| {
+        Some(
+            dialogues.get(&run.dialogue)?.nodes[&run.node]
+                .text
+                .chars()
+                .count(),
+        )
+    }) else {
+        return;
+    };
+    if let Some(run) = active.0.as_mut()
+        && run.shown < length as f32
+    {
+        run.shown = (run.shown + TYPE_SPEED * time.delta_secs()).min(length as f32);
+    }
+}
+
+fn update_dialogue_box(
+    active: Res<ActiveDialogue>,
+    dialogues: Res<Assets<Dialogue>>,
+    mut root: Single<&mut Visibility, With<DialogueBox>>,
+    portrait: Single<(&mut ImageNode, &mut BackgroundColor), With<DialoguePortrait>>,
+    mut speaker_text: Single<
+        (&mut Text, &mut TextColor),
+        (
+            With<DialogueSpeaker>,
+            Without<DialogueText>,
+            Without<DialogueChoices>,
+        ),
+    >,
+    mut body_text: Single<
+        &mut Text,
+        (
+            With<DialogueText>,
+            Without<DialogueSpeaker>,
+            Without<DialogueChoices>,
+        ),
+    >,
+    mut choices_text: Single<
+        &mut Text,
+        (
+            With<DialogueChoices>,
+            Without<DialogueSpeaker>,
+            Without<DialogueText>,
+        ),
+    >,
+) {
+    if !active.is_changed() {
+        return;
+    }
+
+    let Some((run, dialogue)) = active
+        .0
+        .as_ref()
+        .and_then(|run| Some((run, dialogues.get(&run.dialogue)?)))
+    else {
+        **root = Visibility::Hidden;
+        return;
+    };
+    **root = Visibility::Visible;
+
+    let node = &dialogue.nodes[&run.node];
+    let speaker = &dialogue.speakers[&node.speaker];
+    let (r, g, b) = speaker.color;
+    let color = Color::srgb(r, g, b);
+
+    let (mut image, mut background) = portrait.into_inner();
+    image.image = speaker.portrait_image.clone().unwrap_or_default();
+    background.0 = color.with_alpha(0.4);
+
+    speaker_text.0.0 = node.speaker.clone();
+    speaker_text.1.0 = color;
+    body_text.0 = node.text.chars().take(run.shown as usize).collect();
+
+    // Choices only appear once the line has finished typing
+    let typed = run.shown as usize >= node.text.chars().count();
+    choices_text.0 = if typed {
+        node.choices
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| {
+                let marker = if i == run.selected { ">" } else { " " };
+                format!("{} {}", marker, choice.text)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        String::new()
+    };
+}
+
+fn close_dialogue(mut active: ResMut<ActiveDialogue>) {
+    **active = None;
+}
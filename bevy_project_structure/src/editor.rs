@@ -0,0 +1,367 @@
+use bevy::{input::mouse::AccumulatedMouseScroll, prelude::*};
+
+use crate::{
+    GameState, TILE_SIZE,
+    campaign::CurrentLevel,
+    level::{BrickSheet, LevelHandle},
+    level_data::{LevelData, Tile, tile_to_world, world_to_tile},
+    loading::despawn_with,
+    trigger::draw_trigger_shape,
+    viewport::VirtualViewport,
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F1;
+const PAN_SPEED: f32 = 800.;
+const BRICK_VARIANTS: usize = 4;
+
+#[derive(Component)]
+struct EditorTile;
+
+/// Preview of the brick that will be placed under the mouse
+#[derive(Component)]
+struct EditorCursor;
+
+#[derive(Component)]
+struct EditorHud;
+
+/// Frame of bricks.png placed with the left mouse button
+#[derive(Resource, Default, Deref, DerefMut)]
+struct SelectedVariant(usize);
+
+/// Sent whenever the level asset changes so the tiles can be redrawn
+#[derive(Event)]
+struct LevelEdited;
+
+pub struct EditorPlugin;
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedVariant>()
+            .add_event::<LevelEdited>()
+            .add_systems(
+                Update,
+                toggle_editor.run_if(in_state(GameState::Playing).or(in_state(GameState::Editor))),
+            )
+            .add_systems(OnEnter(GameState::Editor), (setup_editor, draw_level))
+            .add_systems(
+                Update,
+                (
+                    pan_camera,
+                    select_variant,
+                    edit_tiles,
+                    save_load,
+                    draw_level.run_if(on_event::<LevelEdited>),
+                    move_cursor,
+                    update_hud,
+                    draw_grid,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Editor)),
+            )
+            .add_systems(
+                OnExit(GameState::Editor),
+                (
+                    despawn_with::<EditorTile>,
+                    despawn_with::<EditorCursor>,
+                    despawn_with::<EditorHud>,
+                    reset_camera_height,
+                ),
+            );
+    }
+}
+
+fn toggle_editor(
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if input.just_pressed(TOGGLE_KEY) {
+        next_state.set(match state.get() {
+            GameState::Editor => GameState::Playing,
+            _ => GameState::Editor,
+        });
+    }
+}
+
+fn setup_editor(mut commands: Commands, brick_sheet: Res<BrickSheet>) {
+    let mut cursor = Sprite::from_atlas_image(
+        brick_sheet.0.clone(),
+        TextureAtlas {
+            layout: brick_sheet.1.clone(),
+            index: 0,
+        },
+    );
+    cursor.color = Color::WHITE.with_alpha(0.5);
+    commands.spawn((cursor, Transform::default(), EditorCursor));
+
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 20.,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        },
+        EditorHud,
+    ));
+}
+
+/// Respawn a sprite for every tile of the level
+fn draw_level(
+    mut commands: Commands,
+    tiles: Query<Entity, With<EditorTile>>,
+    levels: Res<Assets<LevelData>>,
+    level_handle: Res<LevelHandle>,
+    brick_sheet: Res<BrickSheet>,
+    viewport: Res<VirtualViewport>,
+) {
+    for entity in &tiles {
+        commands.entity(entity).despawn();
+    }
+
+    let Some(level) = levels.get(&**level_handle) else {
+        return;
+    };
+
+    for (col, row, tile) in level.tiles() {
+        let sprite = match tile {
+            Tile::Brick(index) => Sprite::from_atlas_image(
+                brick_sheet.0.clone(),
+                TextureAtlas {
+                    layout: brick_sheet.1.clone(),
+                    index,
+                },
+            ),
+            // Everything else only needs to be recognizable
+            Tile::Checkpoint => marker(Color::srgb(0.2, 0.8, 0.3)),
+            Tile::Hazard => marker(Color::srgb(0.85, 0.15, 0.15)),
+            Tile::Collectible => marker(Color::srgb(1., 0.85, 0.1)),
+            Tile::Enemy => marker(Color::srgb(0.6, 0.2, 0.8)),
+            Tile::Npc => marker(Color::srgb(0.6, 1., 0.7)),
+            Tile::Empty => continue,
+        };
+
+        commands.spawn((
+            sprite,
+            Transform::from_translation(tile_to_world(&viewport, col, row).extend(1.)),
+            EditorTile,
+        ));
+    }
+}
+
+fn marker(color: Color) -> Sprite {
+    Sprite::from_color(color, Vec2::splat(TILE_SIZE * 0.6))
+}
+
+/// Gameplay only ever moves the camera sideways, so undo any panning up or down
+fn reset_camera_height(mut camera: Single<&mut Transform, With<Camera>>) {
+    camera.translation.y = 0.;
+}
+
+fn pan_camera(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut camera: Single<&mut Transform, With<Camera>>,
+) {
+    let mut dir = Vec2::ZERO;
+
+    if input.pressed(KeyCode::ArrowLeft) {
+        dir.x -= 1.;
+    }
+
+    if input.pressed(KeyCode::ArrowRight) {
+        dir.x += 1.;
+    }
+
+    if input.pressed(KeyCode::ArrowUp) {
+        dir.y += 1.;
+    }
+
+    if input.pressed(KeyCode::ArrowDown) {
+        dir.y -= 1.;
+    }
+
+    camera.translation += (dir.normalize_or_zero() * PAN_SPEED * time.delta_secs()).extend(0.);
+}
+
+fn select_variant(
+    scroll: Res<AccumulatedMouseScroll>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut selected: ResMut<SelectedVariant>,
+) {
+    if scroll.delta.y > 0. || input.just_pressed(KeyCode::KeyE) {
+        **selected = (**selected + 1) % BRICK_VARIANTS;
+    } else if scroll.delta.y < 0. || input.just_pressed(KeyCode::KeyQ) {
+        **selected = (**selected + BRICK_VARIANTS - 1) % BRICK_VARIANTS;
+    }
+}
+
+/// Tile under the mouse, if the mouse is over the window
+fn hovered_tile(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    viewport: &VirtualViewport,
+) -> Option<(usize, usize)> {
+    let cursor = window.cursor_position()?;
+    let world = camera.viewport_to_world_2d(camera_transform, cursor).ok()?;
+    world_to_tile(viewport, world)
+}
+
+fn edit_tiles(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    level_handle: Res<LevelHandle>,
+    mut levels: ResMut<Assets<LevelData>>,
+    selected: Res<SelectedVariant>,
+    viewport: Res<VirtualViewport>,
+    mut edited: EventWriter<LevelEdited>,
+) {
+    // Only borrow the level mutably when something might change, since that
+    // marks the asset as modified
+    if !mouse.any_pressed([MouseButton::Left, MouseButton::Right])
+        && !mouse.just_pressed(MouseButton::Middle)
+    {
+        return;
+    }
+
+    let (camera, camera_transform) = camera.into_inner();
+    let Some((col, row)) = hovered_tile(&window, camera, camera_transform, &viewport) else {
+        return;
+    };
+    let Some(level) = levels.get_mut(&**level_handle) else {
+        return;
+    };
+    if row >= level.rows() {
+        return;
+    }
+
+    let current = level.tile(col, row);
+    let new = if mouse.pressed(MouseButton::Left) {
+        Tile::Brick(**selected)
+    } else if mouse.pressed(MouseButton::Right) {
+        Tile::Empty
+    } else if mouse.just_pressed(MouseButton::Middle)
+        && let Tile::Brick(index) = current
+    {
+        Tile::Brick((index + 1) % BRICK_VARIANTS)
+    } else {
+        return;
+    };
+
+    if new != current {
+        level.set_tile(col, row, new);
+        edited.write(LevelEdited);
+    }
+}
+
+fn save_load(
+    input: Res<ButtonInput<KeyCode>>,
+    current_level: Res<CurrentLevel>,
+    level_handle: Res<LevelHandle>,
+    mut levels: ResMut<Assets<LevelData>>,
+    mut edited: EventWriter<LevelEdited>,
+) {
+    if !input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !input.any_just_pressed([KeyCode::KeyS, KeyCode::KeyL])
+    {
+        return;
+    }
+    let Some(level) = levels.get_mut(&**level_handle) else {
+        return;
+    };
+    let path = current_level.source().save_path();
+
+    if input.just_pressed(KeyCode::KeyS) {
+        match level.save_file(&path) {
+            Ok(()) => info!("Saved level to {}", path),
+            Err(e) => error!("Could not save level to {}: {}", path, e),
+        }
+    } else if input.just_pressed(KeyCode::KeyL) {
+        match LevelData::load_file(&path) {
+            Ok(loaded) => {
+                info!("Loaded level from {}", path);
+                *level = loaded;
+                edited.write(LevelEdited);
+            }
+            Err(e) => error!("Could not load level from {}: {}", path, e),
+        }
+    }
+}
+
+fn move_cursor(
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    selected: Res<SelectedVariant>,
+    viewport: Res<VirtualViewport>,
+    cursor: Single<(&mut Transform, &mut Sprite, &mut Visibility), With<EditorCursor>>,
+) {
+    let (camera, camera_transform) = camera.into_inner();
+    let (mut transform, mut sprite, mut visibility) = cursor.into_inner();
+
+    match hovered_tile(&window, camera, camera_transform, &viewport) {
+        Some((col, row)) => {
+            transform.translation = tile_to_world(&viewport, col, row).extend(2.);
+            *visibility = Visibility::Visible;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+
+    if let Some(atlas) = &mut sprite.texture_atlas {
+        atlas.index = **selected;
+    }
+}
+
+fn update_hud(
+    selected: Res<SelectedVariant>,
+    current_level: Res<CurrentLevel>,
+    mut hud: Single<&mut Text, With<EditorHud>>,
+) {
+    hud.0 = format!(
+        "EDITOR {}\n\
+         Brick {} of {} (wheel or Q/E)\n\
+         LMB place, RMB erase, MMB cycle brick\n\
+         Arrows pan, Ctrl+S save, Ctrl+L load, F1 play",
+        current_level.source().save_path(),
+        **selected + 1,
+        BRICK_VARIANTS,
+    );
+}
+
+fn draw_grid(
+    mut gizmos: Gizmos,
+    levels: Res<Assets<LevelData>>,
+    level_handle: Res<LevelHandle>,
+    viewport: Res<VirtualViewport>,
+) {
+    let Some(level) = levels.get(&**level_handle) else {
+        return;
+    };
+
+    // One column past the end, so there is somewhere to widen the level into
+    let cells = UVec2::new(level.columns() as u32 + 1, level.rows() as u32);
+    let size = cells.as_vec2() * TILE_SIZE;
+    let center = viewport.min() + size / 2.;
+    gizmos
+        .grid_2d(
+            center,
+            cells,
+            Vec2::splat(TILE_SIZE),
+            Color::WHITE.with_alpha(0.2),
+        )
+        .outer_edges();
+
+    // Triggers aren't editable here, but should be seen when moving tiles
+    for trigger in &level.triggers {
+        draw_trigger_shape(
+            &mut gizmos,
+            trigger.position(&viewport),
+            trigger.shape.scaled(TILE_SIZE),
+            Color::srgb(1., 0.5, 0.),
+        );
+    }
+}
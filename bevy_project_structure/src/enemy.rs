@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState, TILE_SIZE,
+    collision::Collider,
+    console::ConsoleExt,
+    hazard::Hazard,
+    level::LevelHandle,
+    level_data::LevelData,
+    loading::despawn_with,
+    player::Player,
+    timeline::{Timeline, WaveMember, Waves},
+    viewport::VirtualViewport,
+};
+
+const ENEMY_COLOR: Color = Color::srgb(0.6, 0.2, 0.8);
+const ENEMY_SPEED: f32 = 150.;
+/// How far either side of its spawn an enemy walks before turning around
+const PATROL_RANGE: f32 = TILE_SIZE * 2.;
+/// Seconds between footsteps
+const STEP_TIME: f32 = 0.45;
+
+/// Enemy types a level's wave timeline can send
+pub const WAVE_ENEMY_NAMES: [&str; 2] = ["drone", "heavy"];
+
+/// Walks back and forth, and hurts like any other hazard
+#[derive(Component)]
+pub struct Enemy {
+    origin: f32,
+    dir: f32,
+    step: Timer,
+}
+
+/// Sent each time an enemy's foot comes down, for footstep sounds
+#[derive(Event)]
+pub struct EnemyStepped {
+    pub position: Vec2,
+}
+
+pub struct EnemyPlugin;
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnemyStepped>()
+            .add_systems(Update, patrol.run_if(in_state(GameState::Playing)))
+            .add_systems(OnEnter(GameState::Playing), start_waves)
+            .add_systems(
+                Update,
+                (spawn_waves.run_if(resource_exists::<Waves>), fly_waves)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_with::<Enemy>)
+            .add_console_command(
+                "spawn enemy",
+                "spawn enemy - put an enemy two tiles ahead of the player",
+                spawn_enemy_command,
+            );
+    }
+}
+
+pub fn spawn_enemy(commands: &mut Commands, position: Vec2) {
+    let size = Vec2::splat(TILE_SIZE * 0.6);
+    commands.spawn((
+        Sprite::from_color(ENEMY_COLOR, size),
+        // Stand on the floor of the tile
+        Transform::from_translation(
+            (position - Vec2::new(0., (TILE_SIZE - size.y) / 2.)).extend(3.),
+        ),
+        Collider(size / 2.),
+        Hazard,
+        Enemy {
+            origin: position.x,
+            dir: 1.,
+            step: Timer::from_seconds(STEP_TIME, TimerMode::Repeating),
+        },
+    ));
+}
+
+fn patrol(
+    time: Res<Time>,
+    mut enemies: Query<(&mut Transform, &mut Enemy)>,
+    mut stepped: EventWriter<EnemyStepped>,
+) {
+    for (mut transform, mut enemy) in enemies.iter_mut() {
+        if enemy.step.tick(time.delta()).just_finished() {
+            stepped.write(EnemyStepped {
+                position: transform.translation.truncate(),
+            });
+        }
+
+        transform.translation.x += enemy.dir * ENEMY_SPEED * time.delta_secs();
+
+        let offset = transform.translation.x - enemy.origin;
+        if offset.abs() > PATROL_RANGE {
+            transform.translation.x = enemy.origin + offset.signum() * PATROL_RANGE;
+            enemy.dir = -offset.signum();
+        }
+    }
+}
+
+/// Colour and size of a wave enemy, by type
+fn wave_enemy_look(enemy: &str) -> (Color, f32) {
+    match enemy {
+        "heavy" => (Color::srgb(0.45, 0.1, 0.6), TILE_SIZE * 0.7),
+        _ => (Color::srgb(0.85, 0.4, 0.9), TILE_SIZE * 0.4),
+    }
+}
+
+/// Play the level's wave timeline from the top, counting scroll distance from
+/// wherever the camera is now
+fn start_waves(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    levels: Res<Assets<LevelData>>,
+    level_handle: Res<LevelHandle>,
+    camera: Single<&Transform, With<Camera>>,
+) {
+    match levels
+        .get(&**level_handle)
+        .and_then(|level| level.waves.as_ref())
+    {
+        Some(path) => {
+            let mut waves = Waves::load(&asset_server, path, WAVE_ENEMY_NAMES.to_vec());
+            waves.restart(camera.translation.x);
+            commands.insert_resource(waves);
+        }
+        None => commands.remove_resource::<Waves>(),
+    }
+}
+
+fn spawn_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    timelines: Res<Assets<Timeline>>,
+    mut waves: ResMut<Waves>,
+    viewport: Res<VirtualViewport>,
+    camera: Single<&Transform, With<Camera>>,
+) {
+    for spawn in waves.advance(&timelines, time.delta_secs(), camera.translation.x) {
+        let (color, size) = wave_enemy_look(&spawn.enemy);
+        for member in WaveMember::members(&spawn, size / 2.) {
+            let position = member.position(viewport.width());
+            commands.spawn((
+                Sprite::from_color(color, Vec2::splat(size)),
+                Transform::from_xyz(camera.translation.x + position.x, position.y, 3.),
+                Collider(Vec2::splat(size / 2.)),
+                // Cleaned up with the other hazards when the level ends
+                Hazard,
+                member,
+            ));
+        }
+    }
+}
+
+/// Wave enemies cross the view whichever way the player runs
+fn fly_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    viewport: Res<VirtualViewport>,
+    camera: Single<&Transform, (With<Camera>, Without<WaveMember>)>,
+    mut members: Query<(Entity, &mut Transform, &mut WaveMember)>,
+) {
+    let width = viewport.width();
+    for (entity, mut transform, mut member) in members.iter_mut() {
+        member.age += time.delta_secs();
+        if member.gone(width) {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let position = member.position(width);
+        transform.translation.x = camera.translation.x + position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+fn spawn_enemy_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let player = world
+        .query_filtered::<&Transform, With<Player>>()
+        .single(world)
+        .map_err(|_| "no player to spawn near")?;
+    let position = player.translation.truncate() + Vec2::new(TILE_SIZE * 2., 0.);
+
+    spawn_enemy(&mut world.commands(), position);
+    Ok(format!("spawned enemy at {}", position))
+}
@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::PathBuf};
+
+use crate::{
+    GameState,
+    loading::despawn_with,
+    player::{Player, PlayerSheet},
+    speedrun::{NewPersonalBest, Speedrun, start_run},
+    viewport::VirtualViewport,
+};
+
+const GHOST_COLOR: Color = Color::srgba(0.7, 0.85, 1., 0.4);
+
+/// Where the player was and which frame of the walking sheet it showed
+///
+/// Stored as time into the run, x and y measured from the bottom left of the
+/// level, and the atlas index.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct GhostFrame(f32, f32, f32, usize);
+
+/// Every frame of the best run of one level
+#[derive(Serialize, Deserialize)]
+struct GhostRun {
+    level: String,
+    time: f32,
+    frames: Vec<GhostFrame>,
+}
+
+impl GhostRun {
+    fn load(level: &str) -> Option<Self> {
+        let path = ghost_path(level);
+        let text = fs::read_to_string(&path).ok()?;
+        ron::de::from_str(&text)
+            .inspect_err(|e| warn!("Ignoring unreadable {}: {}", path.display(), e))
+            .ok()
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let text = ron::ser::to_string(self)?;
+        let path = ghost_path(&self.level);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Position and atlas index at `time`, holding on the last frame once the
+    /// run is over
+    fn sample(&self, time: f32) -> Option<(Vec2, usize)> {
+        let next = self.frames.partition_point(|frame| frame.0 <= time);
+        let GhostFrame(t0, x0, y0, index) = *self.frames.get(next.checked_sub(1)?)?;
+        let position = match self.frames.get(next) {
+            Some(&GhostFrame(t1, x1, y1, _)) if t1 > t0 => {
+                Vec2::new(x0, y0).lerp(Vec2::new(x1, y1), (time - t0) / (t1 - t0))
+            }
+            _ => Vec2::new(x0, y0),
+        };
+        Some((position, index))
+    }
+}
+
+/// Ghosts are kept in the OS data folder, one file per level
+fn ghost_path(level: &str) -> PathBuf {
+    let name: String = level
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dirs::data_dir()
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+        .unwrap_or_default()
+        .join("ghosts")
+        .join(format!("{}.ghost.ron", name))
+}
+
+/// Frames of the run in progress
+#[derive(Resource, Default, Deref, DerefMut)]
+struct Recording(Vec<GhostFrame>);
+
+#[derive(Component)]
+struct Ghost(GhostRun);
+
+pub struct GhostPlugin;
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>()
+            .add_systems(
+                OnTransition {
+                    exited: GameState::Loading,
+                    entered: GameState::Playing,
+                },
+                (clear_recording, spawn_ghost.after(start_run)),
+            )
+            .add_systems(Update, play_ghost.run_if(in_state(GameState::Playing)))
+            // After everything has moved for the frame
+            .add_systems(
+                PostUpdate,
+                record_frame.run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Update, save_ghost.run_if(on_event::<NewPersonalBest>))
+            .add_systems(OnExit(GameState::Playing), despawn_with::<Ghost>);
+    }
+}
+
+fn clear_recording(mut recording: ResMut<Recording>) {
+    recording.clear();
+}
+
+fn spawn_ghost(
+    mut commands: Commands,
+    run: Res<Speedrun>,
+    player_sheet: Res<PlayerSheet>,
+    viewport: Res<VirtualViewport>,
+) {
+    let Some(ghost) = GhostRun::load(&run.level) else {
+        return;
+    };
+    let Some((position, index)) = ghost.sample(0.) else {
+        return;
+    };
+
+    let mut sprite = Sprite::from_atlas_image(
+        player_sheet.0.clone(),
+        TextureAtlas {
+            layout: player_sheet.1.clone(),
+            index,
+        },
+    );
+    sprite.color = GHOST_COLOR;
+    commands.spawn((
+        sprite,
+        // Just behind the player
+        Transform::from_translation((position + viewport.min()).extend(899.)),
+        Ghost(ghost),
+    ));
+}
+
+fn play_ghost(
+    run: Res<Speedrun>,
+    viewport: Res<VirtualViewport>,
+    mut ghosts: Query<(&mut Transform, &mut Sprite, &Ghost)>,
+) {
+    for (mut transform, mut sprite, Ghost(ghost)) in ghosts.iter_mut() {
+        let Some((position, index)) = ghost.sample(run.time) else {
+            continue;
+        };
+        let position = position + viewport.min();
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        if let Some(atlas) = &mut sprite.texture_atlas {
+            atlas.index = index;
+        }
+    }
+}
+
+fn record_frame(
+    run: Res<Speedrun>,
+    viewport: Res<VirtualViewport>,
+    player: Single<(&Transform, &Sprite), With<Player>>,
+    mut recording: ResMut<Recording>,
+) {
+    if !run.running {
+        return;
+    }
+
+    let (transform, sprite) = player.into_inner();
+    let position = transform.translation.truncate() - viewport.min();
+    let index = sprite.texture_atlas.as_ref().map_or(0, |atlas| atlas.index);
+    recording.push(GhostFrame(run.time, position.x, position.y, index));
+}
+
+fn save_ghost(
+    mut new_best: EventReader<NewPersonalBest>,
+    run: Res<Speedrun>,
+    mut recording: ResMut<Recording>,
+) {
+    new_best.clear();
+
+    let ghost = GhostRun {
+        level: run.level.clone(),
+        time: run.time,
+        frames: std::mem::take(&mut **recording),
+    };
+    match ghost.save() {
+        Ok(()) => info!("Saved a ghost of the run"),
+        Err(e) => error!("Could not save ghost: {}", e),
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState, TILE_SIZE,
+    collision::{Collider, overlaps},
+    loading::despawn_with,
+    player::{Player, PlayerDied},
+};
+
+const HAZARD_COLOR: Color = Color::srgb(0.85, 0.15, 0.15);
+
+/// Kills the player on touch
+#[derive(Component)]
+pub struct Hazard;
+
+pub struct HazardPlugin;
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, touch_hazards.run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), despawn_with::<Hazard>);
+    }
+}
+
+/// Spikes sit on the floor of their tile
+pub fn spawn_hazard(commands: &mut Commands, position: Vec2) {
+    let size = Vec2::new(TILE_SIZE, TILE_SIZE / 3.);
+    commands.spawn((
+        Sprite::from_color(HAZARD_COLOR, size),
+        Transform::from_translation(
+            (position - Vec2::new(0., (TILE_SIZE - size.y) / 2.)).extend(2.),
+        ),
+        Collider(size / 2.),
+        Hazard,
+    ));
+}
+
+fn touch_hazards(
+    player: Single<(&Transform, &Collider), With<Player>>,
+    hazards: Query<(&Transform, &Collider), (With<Hazard>, Without<Player>)>,
+    mut died: EventWriter<PlayerDied>,
+) {
+    let (player_transform, player_collider) = player.into_inner();
+    let player_box = player_collider.bounds(player_transform);
+
+    if hazards
+        .iter()
+        .any(|(transform, collider)| overlaps(&player_box, &collider.bounds(transform)))
+    {
+        died.write(PlayerDied {
+            position: player_transform.translation.truncate(),
+        });
+    }
+}
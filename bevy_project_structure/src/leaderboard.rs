@@ -0,0 +1,556 @@
+use bevy::{
+    input::{
+        InputSystem,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::PathBuf};
+
+use crate::{
+    GameState,
+    campaign::{CurrentLevel, Progress},
+    console::ConsoleSystems,
+    loading::despawn_with,
+    options::OptionsSystems,
+    speedrun::{Speedrun, finish_run, format_time, start_run},
+    win::{Win, win_event_listener},
+};
+
+const LEADERBOARD_FILE: &str = "leaderboard.ron";
+/// Entries kept for each level
+const TOP_N: usize = 10;
+const MAX_NAME_LEN: usize = 12;
+/// Letters a gamepad steps through, in order
+const NAME_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+const OPEN_KEY: KeyCode = KeyCode::KeyL;
+const HIGHLIGHT_COLOR: Color = Color::srgb(1., 0.85, 0.1);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u32,
+    /// Seconds to finish the level
+    pub time: f32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct LevelBoard {
+    level: String,
+    entries: Vec<ScoreEntry>,
+}
+
+/// Best results of each level, highest score first and the faster time
+/// breaking ties
+///
+/// Levels are listed in the order they were first cleared.
+#[derive(Resource, Default)]
+pub struct Leaderboards(Vec<LevelBoard>);
+
+impl Leaderboards {
+    fn load() -> Self {
+        let path = leaderboard_path();
+        match fs::read_to_string(&path) {
+            Ok(text) => ron::de::from_str(&text).map(Self).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let text = ron::ser::to_string_pretty(&self.0, ron::ser::PrettyConfig::default())?;
+        let path = leaderboard_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Adds `entry` to the table of `level` if it makes the top `TOP_N`,
+    /// returning its place
+    fn insert(&mut self, level: &str, entry: ScoreEntry) -> Option<usize> {
+        let index = match self.0.iter().position(|board| board.level == level) {
+            Some(index) => index,
+            None => {
+                self.0.push(LevelBoard {
+                    level: level.to_string(),
+                    entries: Vec::new(),
+                });
+                self.0.len() - 1
+            }
+        };
+        let entries = &mut self.0[index].entries;
+
+        let place = entries.partition_point(|other| {
+            other.score > entry.score || (other.score == entry.score && other.time <= entry.time)
+        });
+        entries.insert(place, entry);
+        entries.truncate(TOP_N);
+        (place < TOP_N).then_some(place)
+    }
+}
+
+/// Leaderboards are kept in the OS data folder, next to the saves
+fn leaderboard_path() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+        .unwrap_or_default()
+        .join(LEADERBOARD_FILE)
+}
+
+/// Score and time of each level cleared since the campaign was started, to be
+/// entered under one name at the end
+#[derive(Resource, Default)]
+struct CampaignResults {
+    results: Vec<(String, ScoreEntry)>,
+    score_at_start: u32,
+}
+
+/// Sent to bring up the leaderboard, from the options menu or the Win screen
+#[derive(Event, Default)]
+pub struct ShowLeaderboard;
+
+#[derive(Resource, Default)]
+struct LeaderboardScreen {
+    open: bool,
+    /// Index into `Leaderboards` of the level shown
+    board: usize,
+    /// Entry just added, to highlight
+    highlight: Option<(usize, usize)>,
+}
+
+/// Name being typed on the Win screen
+///
+/// A keyboard types as usual. A gamepad's d-pad steps the last letter up and
+/// down through `NAME_CHARS`, right adds a letter and left removes one.
+#[derive(Resource, Default)]
+struct NameEntry {
+    active: bool,
+    name: String,
+}
+
+impl NameEntry {
+    fn push(&mut self, c: char) {
+        if self.name.chars().count() < MAX_NAME_LEN {
+            self.name.push(c);
+        }
+    }
+
+    fn step_last(&mut self, step: isize) {
+        let chars: Vec<char> = NAME_CHARS.chars().collect();
+        let last = self.name.pop().unwrap_or('A').to_ascii_uppercase();
+        let index = chars.iter().position(|&c| c == last).unwrap_or(0);
+        self.name
+            .push(chars[(index as isize + step).rem_euclid(chars.len() as isize) as usize]);
+    }
+}
+
+#[derive(Component)]
+struct NameEntryUi;
+
+#[derive(Component)]
+struct LeaderboardRoot;
+
+#[derive(Component)]
+struct LeaderboardTitle;
+
+#[derive(Component)]
+struct LeaderboardRows;
+
+pub struct LeaderboardPlugin;
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leaderboards::load())
+            .init_resource::<CampaignResults>()
+            .init_resource::<LeaderboardScreen>()
+            .init_resource::<NameEntry>()
+            .add_event::<ShowLeaderboard>()
+            .add_systems(Startup, setup_leaderboard)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::Loading,
+                    entered: GameState::Playing,
+                },
+                start_level.after(start_run),
+            )
+            // Before the Win screen asks for a name to go with the result
+            .add_systems(
+                Update,
+                record_result.after(finish_run).before(win_event_listener),
+            )
+            .add_systems(OnEnter(GameState::Win), setup_name_entry)
+            .add_systems(OnExit(GameState::Win), despawn_with::<NameEntryUi>)
+            // Take the keyboard after the console but before the options menu,
+            // so Esc closes the leaderboard rather than opening the menu
+            .add_systems(
+                PreUpdate,
+                (
+                    enter_name.run_if(in_state(GameState::Win)),
+                    navigate_leaderboard,
+                )
+                    .chain()
+                    .after(InputSystem)
+                    .after(ConsoleSystems)
+                    .before(OptionsSystems),
+            )
+            .add_systems(Update, (open_leaderboard, update_leaderboard).chain())
+            .add_systems(Update, update_name_entry.run_if(in_state(GameState::Win)));
+    }
+}
+
+fn setup_leaderboard(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.8)),
+            Visibility::Hidden,
+            // Over the options menu
+            GlobalZIndex(45),
+            LeaderboardRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("LEADERBOARD"),
+                TextFont {
+                    font_size: 40.,
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 24.,
+                    ..default()
+                },
+                LeaderboardTitle,
+            ));
+            parent
+                .spawn((
+                    Text::default(),
+                    TextFont {
+                        font_size: 20.,
+                        ..default()
+                    },
+                    LeaderboardRows,
+                ))
+                .with_child((
+                    // Highlighted entry and everything after it
+                    TextSpan::default(),
+                    TextFont {
+                        font_size: 20.,
+                        ..default()
+                    },
+                    TextColor(HIGHLIGHT_COLOR),
+                ))
+                .with_child((
+                    TextSpan::default(),
+                    TextFont {
+                        font_size: 20.,
+                        ..default()
+                    },
+                ));
+            parent.spawn((
+                Text::new("Left/Right change level, Esc or B close"),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn start_level(
+    current_level: Res<CurrentLevel>,
+    progress: Res<Progress>,
+    mut results: ResMut<CampaignResults>,
+) {
+    if **current_level == 0 {
+        results.results.clear();
+    }
+    results.score_at_start = progress.score;
+}
+
+fn record_result(
+    mut win: EventReader<Win>,
+    run: Res<Speedrun>,
+    progress: Res<Progress>,
+    mut results: ResMut<CampaignResults>,
+) {
+    if win.is_empty() {
+        return;
+    }
+    win.clear();
+
+    // Same rules as personal bests
+    if run.ranked && !run.running {
+        let entry = ScoreEntry {
+            name: String::new(),
+            score: progress.score.saturating_sub(results.score_at_start),
+            time: run.time,
+        };
+        results.results.push((run.level.clone(), entry));
+    }
+}
+
+fn setup_name_entry(
+    mut commands: Commands,
+    results: Res<CampaignResults>,
+    mut entry: ResMut<NameEntry>,
+) {
+    entry.active = !results.results.is_empty();
+    if !entry.active {
+        return;
+    }
+
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 28.,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.),
+            width: Val::Percent(100.),
+            ..default()
+        },
+        NameEntryUi,
+    ));
+}
+
+fn enter_name(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut entry: ResMut<NameEntry>,
+    mut results: ResMut<CampaignResults>,
+    mut leaderboards: ResMut<Leaderboards>,
+    mut screen: ResMut<LeaderboardScreen>,
+    mut show: EventWriter<ShowLeaderboard>,
+) {
+    if !entry.active || screen.open {
+        keyboard_events.clear();
+        return;
+    }
+
+    let mut submit = false;
+    for event in keyboard_events.read() {
+        // Keys the console has already taken are no longer pressed
+        if !event.state.is_pressed() || !keys.just_pressed(event.key_code) {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => submit = true,
+            Key::Backspace => {
+                entry.name.pop();
+            }
+            Key::Space => entry.push(' '),
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| c.is_alphanumeric()) {
+                    entry.push(c);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for gamepad in &gamepads {
+        if gamepad.just_pressed(GamepadButton::DPadUp) {
+            entry.step_last(-1);
+        }
+        if gamepad.just_pressed(GamepadButton::DPadDown) {
+            entry.step_last(1);
+        }
+        if gamepad.just_pressed(GamepadButton::DPadRight) {
+            entry.push('A');
+        }
+        if gamepad.just_pressed(GamepadButton::DPadLeft) {
+            entry.name.pop();
+        }
+        submit |= gamepad.just_pressed(GamepadButton::South)
+            || gamepad.just_pressed(GamepadButton::Start);
+    }
+
+    // Keep typing from also opening menus
+    keys.reset_all();
+
+    let name = entry.name.trim().to_string();
+    if !submit || name.is_empty() {
+        return;
+    }
+
+    entry.active = false;
+    let mut last = None;
+    for (level, mut score) in results.results.drain(..) {
+        score.name = name.clone();
+        let place = leaderboards.insert(&level, score);
+        last = Some((level, place));
+    }
+    if let Err(e) = leaderboards.save() {
+        error!("Could not save the leaderboard: {}", e);
+    }
+
+    // Open on the last level played, with the new entry picked out
+    if let Some((level, place)) = last
+        && let Some(board) = leaderboards.0.iter().position(|board| board.level == level)
+    {
+        screen.board = board;
+        screen.highlight = place.map(|place| (board, place));
+    }
+    show.write(ShowLeaderboard);
+}
+
+fn update_name_entry(entry: Res<NameEntry>, mut ui: Query<&mut Text, With<NameEntryUi>>) {
+    if !entry.is_changed() {
+        return;
+    }
+
+    for mut text in ui.iter_mut() {
+        **text = if entry.active {
+            format!(
+                "Enter your name for the leaderboard: {}_\nEnter or A to confirm",
+                entry.name
+            )
+        } else {
+            String::from("Press L or Y for the leaderboard")
+        };
+    }
+}
+
+fn navigate_leaderboard(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    state: Res<State<GameState>>,
+    entry: Res<NameEntry>,
+    leaderboards: Res<Leaderboards>,
+    mut screen: ResMut<LeaderboardScreen>,
+    mut show: EventWriter<ShowLeaderboard>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let pressed = |key: KeyCode, button: GamepadButton| {
+        keys.just_pressed(key) || gamepads.iter().any(|gamepad| gamepad.just_pressed(button))
+    };
+
+    if !screen.open {
+        // The Win screen has its own way in
+        if *state.get() == GameState::Win
+            && !entry.active
+            && pressed(OPEN_KEY, GamepadButton::North)
+        {
+            show.write(ShowLeaderboard);
+        }
+        return;
+    }
+
+    if pressed(KeyCode::Escape, GamepadButton::East) {
+        screen.open = false;
+        time.unpause();
+    }
+
+    let count = leaderboards.0.len().max(1);
+    if pressed(KeyCode::ArrowRight, GamepadButton::DPadRight) {
+        screen.board = (screen.board + 1) % count;
+    }
+    if pressed(KeyCode::ArrowLeft, GamepadButton::DPadLeft) {
+        screen.board = (screen.board + count - 1) % count;
+    }
+
+    keys.reset_all();
+}
+
+fn open_leaderboard(
+    mut show: EventReader<ShowLeaderboard>,
+    mut screen: ResMut<LeaderboardScreen>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if show.is_empty() {
+        return;
+    }
+    show.clear();
+
+    screen.open = true;
+    // Already paused when coming from the options menu
+    time.pause();
+}
+
+fn update_leaderboard(
+    screen: Res<LeaderboardScreen>,
+    leaderboards: Res<Leaderboards>,
+    mut root: Single<&mut Visibility, With<LeaderboardRoot>>,
+    mut title: Single<&mut Text, With<LeaderboardTitle>>,
+    rows: Single<(&mut Text, &Children), (With<LeaderboardRows>, Without<LeaderboardTitle>)>,
+    mut spans: Query<&mut TextSpan>,
+) {
+    if !screen.is_changed() && !leaderboards.is_changed() {
+        return;
+    }
+
+    **root = if screen.open {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+
+    let (mut rows, children) = rows.into_inner();
+    let Some(board) = leaderboards.0.get(screen.board) else {
+        ***title = String::new();
+        **rows = String::from("No scores yet");
+        for &child in children {
+            if let Ok(mut span) = spans.get_mut(child) {
+                span.clear();
+            }
+        }
+        return;
+    };
+
+    ***title = if leaderboards.0.len() > 1 {
+        format!("< {} >", board.level)
+    } else {
+        board.level.clone()
+    };
+
+    // Lines before, at and after the highlighted entry
+    let highlight = screen
+        .highlight
+        .and_then(|(index, place)| (index == screen.board).then_some(place));
+    let mut parts = [String::new(), String::new(), String::new()];
+    for (place, entry) in board.entries.iter().enumerate() {
+        let part = match highlight {
+            Some(highlighted) if place == highlighted => 1,
+            Some(highlighted) if place > highlighted => 2,
+            _ => 0,
+        };
+        parts[part].push_str(&format!(
+            "{:>2}. {:<width$} {:>5} {:>9}\n",
+            place + 1,
+            entry.name,
+            entry.score,
+            format_time(entry.time),
+            width = MAX_NAME_LEN
+        ));
+    }
+
+    let [before, highlighted, after] = parts;
+    **rows = before;
+    let mut children = children.iter();
+    if let Some(mut span) = children.next().and_then(|child| spans.get_mut(child).ok()) {
+        **span = highlighted;
+    }
+    if let Some(mut span) = children.next().and_then(|child| spans.get_mut(child).ok()) {
+        **span = after;
+    }
+}
@@ -1,8 +1,21 @@
 use bevy::prelude::*;
 
 use crate::{
-    GameState, LEVEL_LEN, TILE_SIZE, WIN_H, WIN_W,
+    GameState, TILE_SIZE,
+    campaign::{CurrentLevel, LevelSource},
+    checkpoint::spawn_checkpoint,
+    collectible::spawn_collectible,
+    collision::{Collider, Solid},
+    console::ConsoleExt,
+    enemy::spawn_enemy,
+    hazard::spawn_hazard,
+    level_data::{LevelData, LevelLoader, Tile, tile_to_world},
+    levelgen::{LevelGenConfig, generate},
     loading::{LoadingAssets, despawn_with},
+    npc::spawn_npc,
+    player::PlayerSheet,
+    trigger::spawn_trigger,
+    viewport::VirtualViewport,
 };
 
 #[derive(Component)]
@@ -14,21 +27,36 @@ pub struct Background;
 #[derive(Resource)]
 pub struct BackgroundImage(Handle<Image>);
 #[derive(Resource)]
-pub struct BrickSheet(Handle<Image>, Handle<TextureAtlasLayout>);
+pub struct BrickSheet(pub Handle<Image>, pub Handle<TextureAtlasLayout>);
+
+#[derive(Resource, Deref)]
+pub struct LevelHandle(Handle<LevelData>);
+
+/// Length of the level currently being played
+#[derive(Resource, Deref)]
+pub struct LevelLength(f32);
 
 pub struct LevelPlugin;
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, load_level)
+        app.init_asset::<LevelData>()
+            .init_asset_loader::<LevelLoader>()
+            .add_systems(Startup, load_level_sheets)
+            .add_systems(OnEnter(GameState::Loading), load_level)
             .add_systems(OnEnter(GameState::Playing), setup_level)
             .add_systems(
                 OnExit(GameState::Playing),
                 (despawn_with::<Brick>, despawn_with::<Background>),
+            )
+            .add_console_command(
+                "reload level",
+                "reload level - read the level file again and restart it",
+                reload_level,
             );
     }
 }
 
-fn load_level(
+fn load_level_sheets(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
@@ -49,50 +77,116 @@ fn load_level(
     commands.insert_resource(BrickSheet(brick_sheet_handle, brick_layout_handle));
 }
 
+fn load_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_level: Res<CurrentLevel>,
+    mut levels: ResMut<Assets<LevelData>>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    let level_handle: Handle<LevelData> = match current_level.source() {
+        LevelSource::File(path) => {
+            info!("Loading level {}: {}", **current_level + 1, path);
+            let handle = asset_server.load(*path);
+            loading_assets.push(handle.clone().untyped());
+            handle
+        }
+        LevelSource::Generated(seed) => {
+            // Already in memory, so there is nothing for the loading screen to wait on
+            info!(
+                "Generating level {} from seed {}",
+                **current_level + 1,
+                seed
+            );
+            levels.add(generate(*seed, &LevelGenConfig::default()))
+        }
+    };
+
+    commands.insert_resource(LevelHandle(level_handle));
+}
+
 fn setup_level(
     mut commands: Commands,
-    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+    levels: Res<Assets<LevelData>>,
+    level_handle: Res<LevelHandle>,
     background_image: Res<BackgroundImage>,
     brick_sheet: Res<BrickSheet>,
+    player_sheet: Res<PlayerSheet>,
+    asset_server: Res<AssetServer>,
+    viewport: Res<VirtualViewport>,
 ) {
+    let Some(level) = levels.get(&**level_handle) else {
+        error!("Level was not loaded before Playing");
+        return;
+    };
+    info!("Starting {}", level.name);
+
     let mut x_offset = 0.;
-    while x_offset < LEVEL_LEN {
+    while x_offset < level.length() {
         commands.spawn((
             Sprite::from_image(background_image.0.clone()),
             Transform::from_xyz(x_offset, 0., 0.),
             Background,
         ));
 
-        x_offset += WIN_W;
+        x_offset += viewport.width();
     }
 
-    let brick_layout = texture_atlases.get(&brick_sheet.1);
-    let brick_layout_len = brick_layout.unwrap().len();
-    let mut i = 0;
-    let mut t = Vec3::new(
-        -WIN_W / 2. + TILE_SIZE / 2.,
-        -WIN_H / 2. + TILE_SIZE / 2.,
-        1.,
-    );
-    while (i as f32) * TILE_SIZE < LEVEL_LEN {
-        commands
-            .spawn((
-                Sprite::from_atlas_image(
-                    brick_sheet.0.clone(),
-                    TextureAtlas {
-                        layout: brick_sheet.1.clone(),
-                        index: i % brick_layout_len,
-                    },
+    let mut npc_dialogue = level.npc_dialogue.iter();
+    for (col, row, tile) in level.tiles() {
+        let position = tile_to_world(&viewport, col, row);
+        match tile {
+            Tile::Brick(index) => {
+                commands.spawn((
+                    Sprite::from_atlas_image(
+                        brick_sheet.0.clone(),
+                        TextureAtlas {
+                            layout: brick_sheet.1.clone(),
+                            index,
+                        },
+                    ),
+                    Transform::from_translation(position.extend(1.)),
+                    Collider(Vec2::splat(TILE_SIZE / 2.)),
+                    Solid,
+                    Brick,
+                ));
+            }
+            Tile::Checkpoint => spawn_checkpoint(&mut commands, position),
+            Tile::Hazard => spawn_hazard(&mut commands, position),
+            Tile::Collectible => spawn_collectible(&mut commands, position),
+            Tile::Enemy => spawn_enemy(&mut commands, position),
+            Tile::Npc => match npc_dialogue.next() {
+                Some(path) => spawn_npc(
+                    &mut commands,
+                    &player_sheet,
+                    asset_server.load(path),
+                    position,
                 ),
-                Transform {
-                    translation: t,
-                    ..default()
-                },
-                Brick,
-            ))
-            .insert(Brick);
-
-        i += 1;
-        t += Vec3::new(TILE_SIZE, 0., 0.);
+                None => warn!("No dialogue for the NPC at column {}", col),
+            },
+            Tile::Empty => {}
+        }
+    }
+
+    for trigger in &level.triggers {
+        spawn_trigger(&mut commands, trigger, &viewport);
     }
+
+    commands.insert_resource(LevelLength(level.length()));
+}
+
+fn reload_level(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    // Generated levels are rebuilt by `load_level` anyway
+    if let LevelSource::File(path) = world.resource::<CurrentLevel>().source() {
+        let level = LevelData::load_file(path).map_err(|e| e.to_string())?;
+        let handle = world.resource::<LevelHandle>().0.clone();
+        world
+            .resource_mut::<Assets<LevelData>>()
+            .insert(&handle, level);
+    }
+
+    world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Loading);
+    Ok(String::from("reloading level"))
 }
@@ -0,0 +1,312 @@
+use bevy::{
+    asset::{
+        AssetLoader, LoadContext,
+        io::{Reader, file::FileAssetReader},
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::PathBuf};
+
+use crate::{TILE_SIZE, viewport::VirtualViewport};
+
+/// On-disk description of a level, stored as RON in `assets/levels/`
+///
+/// `tiles` is a grid of characters read top to bottom, so the last row is the
+/// ground. Each character is one `TILE_SIZE` square:
+///
+/// - `.` empty
+/// - `0`-`3` a brick using that frame of bricks.png
+/// - `C` a checkpoint
+/// - `^` spikes that kill the player
+/// - `*` a collectible
+/// - `E` an enemy that patrols back and forth
+/// - `N` someone to talk to
+///
+/// `music` optionally names a track in the assets folder to play instead of
+/// the default one. `npc_dialogue` lists the dialogue file for each `N`, in
+/// the order they appear reading from the top row down, left to right.
+///
+/// `waves` optionally names a timeline in the assets folder that sends enemies
+/// across the screen as the level goes on, see `timeline.rs`.
+///
+/// `triggers` are zones that do something when the player walks into them,
+/// including the goal that ends the level. A level without a `Goal` can't be
+/// won, so loading one logs a warning.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct LevelData {
+    pub name: String,
+    #[serde(default)]
+    pub music: Option<String>,
+    #[serde(default)]
+    pub npc_dialogue: Vec<String>,
+    #[serde(default)]
+    pub waves: Option<String>,
+    #[serde(default)]
+    pub triggers: Vec<TriggerData>,
+    pub tiles: Vec<String>,
+}
+
+/// A trigger zone as written in a level file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TriggerData {
+    /// Center of the zone in tiles, as column and row up from the ground;
+    /// halves put it on the edge between two tiles
+    pub at: (f32, f32),
+    pub shape: TriggerShape,
+    pub action: TriggerAction,
+}
+
+impl TriggerData {
+    /// Center of the zone in world space
+    pub fn position(&self, viewport: &VirtualViewport) -> Vec2 {
+        tile_to_world(viewport, 0, 0) + Vec2::new(self.at.0, self.at.1) * TILE_SIZE
+    }
+}
+
+/// Measured in tiles in level files, and world units once spawned
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum TriggerShape {
+    /// Width and height
+    Box(f32, f32),
+    Radius(f32),
+}
+
+impl TriggerShape {
+    pub fn scaled(self, scale: f32) -> Self {
+        match self {
+            TriggerShape::Box(width, height) => TriggerShape::Box(width * scale, height * scale),
+            TriggerShape::Radius(radius) => TriggerShape::Radius(radius * scale),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TriggerAction {
+    /// Finish the level
+    Goal,
+    /// Respawn at the middle of the zone after dying
+    Checkpoint,
+    /// Kill the player
+    Hazard,
+    /// Switch to this track, relative to the assets folder, until the level ends
+    Music(String),
+    /// Hold the camera centered on the zone while the player is inside
+    CameraLock,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Empty,
+    Brick(usize),
+    Checkpoint,
+    Hazard,
+    Collectible,
+    Enemy,
+    Npc,
+}
+
+impl Tile {
+    fn from_char(c: char) -> Self {
+        match c {
+            '0'..='3' => Tile::Brick(c as usize - '0' as usize),
+            'C' => Tile::Checkpoint,
+            '^' => Tile::Hazard,
+            '*' => Tile::Collectible,
+            'E' => Tile::Enemy,
+            'N' => Tile::Npc,
+            _ => Tile::Empty,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Tile::Empty => '.',
+            Tile::Brick(index) => char::from(b'0' + index as u8),
+            Tile::Checkpoint => 'C',
+            Tile::Hazard => '^',
+            Tile::Collectible => '*',
+            Tile::Enemy => 'E',
+            Tile::Npc => 'N',
+        }
+    }
+}
+
+impl LevelData {
+    /// Width of the level in tiles
+    pub fn columns(&self) -> usize {
+        self.tiles.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Length of the level in world units
+    pub fn length(&self) -> f32 {
+        self.columns() as f32 * TILE_SIZE
+    }
+
+    /// Tile at `col`, `row` (counted up from the ground), empty if off the grid
+    pub fn tile(&self, col: usize, row: usize) -> Tile {
+        self.rows()
+            .checked_sub(row + 1)
+            .and_then(|line| self.tiles[line].chars().nth(col))
+            .map_or(Tile::Empty, Tile::from_char)
+    }
+
+    /// Change one tile, widening the level if `col` is past its end
+    ///
+    /// Rows outside the grid are ignored, the level's height is fixed. A goal
+    /// on the last column moves out to the new last column when the level
+    /// widens, so the new columns aren't past the end.
+    pub fn set_tile(&mut self, col: usize, row: usize, tile: Tile) {
+        let Some(line) = self.rows().checked_sub(row + 1) else {
+            return;
+        };
+
+        let old_columns = self.columns();
+        let columns = old_columns.max(col + 1);
+        let last_column = old_columns as f32 - 1.;
+        for trigger in self.triggers.iter_mut() {
+            if trigger.action == TriggerAction::Goal && trigger.at.0 >= last_column {
+                trigger.at.0 += (columns - old_columns) as f32;
+            }
+        }
+        for line in self.tiles.iter_mut() {
+            while line.len() < columns {
+                line.push('.');
+            }
+        }
+
+        self.tiles[line].replace_range(col..col + 1, &tile.to_char().to_string());
+    }
+
+    /// Read a level straight from the assets folder, skipping the asset server
+    pub fn load_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(asset_file_path(path))?;
+        let level: Self = ron::de::from_str(&text)?;
+        level.warn_if_unwinnable(path);
+        Ok(level)
+    }
+
+    /// Write a level into the assets folder
+    pub fn save_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        let path = asset_file_path(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn has_goal(&self) -> bool {
+        self.triggers
+            .iter()
+            .any(|trigger| trigger.action == TriggerAction::Goal)
+    }
+
+    fn warn_if_unwinnable(&self, path: &str) {
+        if !self.has_goal() {
+            warn!("{} has no Goal trigger, so it can't be won", path);
+        }
+    }
+
+    /// Every non-empty tile, with its column and row counted up from the ground
+    pub fn tiles(&self) -> impl Iterator<Item = (usize, usize, Tile)> + '_ {
+        let rows = self.rows();
+        self.tiles.iter().enumerate().flat_map(move |(row, line)| {
+            line.chars()
+                .enumerate()
+                .map(move |(col, c)| (col, rows - 1 - row, Tile::from_char(c)))
+                .filter(|(_, _, tile)| *tile != Tile::Empty)
+        })
+    }
+}
+
+/// Center of the tile at `col`, `row` (counted up from the ground) in world space
+///
+/// The first column starts at the left edge of the view, and the ground at
+/// the bottom, while the camera is at the origin.
+pub fn tile_to_world(viewport: &VirtualViewport, col: usize, row: usize) -> Vec2 {
+    viewport.min() + Vec2::new(col as f32 + 0.5, row as f32 + 0.5) * TILE_SIZE
+}
+
+/// Tile containing `position`, if it is inside the level's grid horizontally
+/// and above the bottom of the screen
+pub fn world_to_tile(viewport: &VirtualViewport, position: Vec2) -> Option<(usize, usize)> {
+    let tile = ((position - viewport.min()) / TILE_SIZE).floor();
+    (tile.x >= 0. && tile.y >= 0.).then_some((tile.x as usize, tile.y as usize))
+}
+
+/// Location on disk of a file in the assets folder
+fn asset_file_path(path: &str) -> PathBuf {
+    FileAssetReader::get_base_path().join("assets").join(path)
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = LevelData;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let level: LevelData = ron::de::from_bytes(&bytes)?;
+        level.warn_if_unwinnable(&load_context.path().display().to_string());
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_with_goal_at(col: f32) -> LevelData {
+        LevelData {
+            name: String::from("test"),
+            music: None,
+            npc_dialogue: Vec::new(),
+            waves: None,
+            triggers: vec![TriggerData {
+                at: (col, 0.5),
+                shape: TriggerShape::Box(1., 2.),
+                action: TriggerAction::Goal,
+            }],
+            tiles: vec![String::from("...."), String::from("0000")],
+        }
+    }
+
+    #[test]
+    fn widening_moves_the_goal_to_the_end() {
+        let mut level = level_with_goal_at(3.);
+        level.set_tile(6, 0, Tile::Brick(0));
+        assert_eq!(level.columns(), 7);
+        assert_eq!(level.triggers[0].at.0, 6.);
+
+        // Editing inside the level leaves it alone
+        level.set_tile(2, 1, Tile::Brick(0));
+        assert_eq!(level.triggers[0].at.0, 6.);
+    }
+
+    #[test]
+    fn widening_leaves_an_earlier_goal_alone() {
+        let mut level = level_with_goal_at(1.);
+        level.set_tile(5, 0, Tile::Brick(0));
+        assert_eq!(level.triggers[0].at.0, 1.);
+        assert!(level.has_goal());
+    }
+}
@@ -0,0 +1,330 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    GRAVITY, JUMP_SPEED, PLAYER_SPEED, TILE_SIZE,
+    level_data::{LevelData, Tile, TriggerAction, TriggerData, TriggerShape},
+};
+
+/// Flat ground at the start, wide enough to cover where the player spawns
+const START_RUN: usize = 8;
+/// Flat ground at the end, leading up to the goal
+const END_RUN: usize = 8;
+/// Only rely on this much of the ideal jump, to leave room for sloppy timing
+const JUMP_MARGIN: f32 = 0.8;
+/// Columns between generated checkpoints
+const CHECKPOINT_SPACING: usize = 15;
+
+pub struct LevelGenConfig {
+    pub columns: usize,
+    pub rows: usize,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    pub run_speed: f32,
+    pub hazard_chance: f64,
+    pub collectible_chance: f64,
+    pub platform_chance: f64,
+}
+
+impl Default for LevelGenConfig {
+    fn default() -> Self {
+        Self {
+            columns: 60,
+            rows: 7,
+            jump_speed: JUMP_SPEED,
+            gravity: GRAVITY,
+            run_speed: PLAYER_SPEED,
+            hazard_chance: 0.35,
+            collectible_chance: 0.5,
+            platform_chance: 0.3,
+        }
+    }
+}
+
+impl LevelGenConfig {
+    /// Tallest step, in tiles, the player can jump up onto
+    fn max_rise(&self) -> usize {
+        let peak = self.jump_speed.powi(2) / (2. * self.gravity);
+        (peak * JUMP_MARGIN / TILE_SIZE) as usize
+    }
+
+    /// Widest gap, in tiles, the player can clear running at full speed and
+    /// landing `rise` tiles above (or below, if negative) where they jumped
+    fn max_gap(&self, rise: i32) -> usize {
+        let height = rise as f32 * TILE_SIZE;
+        let discriminant = self.jump_speed.powi(2) - 2. * self.gravity * height;
+        if discriminant < 0. {
+            return 0;
+        }
+
+        // Time until falling back through `height` on the way down
+        let air_time = (self.jump_speed + discriminant.sqrt()) / self.gravity;
+        (self.run_speed * air_time * JUMP_MARGIN / TILE_SIZE) as usize
+    }
+
+    /// Highest the ground can go while leaving room for a platform and
+    /// something to collect on top of it, or just the bottom row in levels
+    /// too short for that
+    fn max_height(&self) -> usize {
+        self.rows.saturating_sub(4).max(1)
+    }
+
+    /// Whether `row` is inside the level, for things placed above the ground
+    fn fits(&self, row: usize) -> bool {
+        row < self.rows
+    }
+}
+
+/// Builds a level from `seed`; the same seed and config always give the same level
+///
+/// Every gap and step is checked against the config's jump, and every jump,
+/// whether over a gap, up a step or over a spike, starts and ends on at least
+/// two tiles of flat ground, so the end is always reachable. A jump too weak
+/// to clear even one tile gets a level with no gaps or spikes at all.
+///
+/// `config.rows` must be at least 2, one for the ground and one to stand in.
+pub fn generate(seed: u64, config: &LevelGenConfig) -> LevelData {
+    assert!(config.rows >= 2, "a level needs at least 2 rows");
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    // Indexed [row][col], with row 0 being the bottom of the screen
+    let mut grid = vec![vec![Tile::Empty; config.columns]; config.rows];
+
+    let last_feature = config.columns.saturating_sub(END_RUN);
+    let mut height = 1;
+    let mut since_checkpoint = 0;
+
+    fill_ground(&mut grid, 0..START_RUN, height);
+    let mut col = START_RUN;
+
+    while col < last_feature {
+        let remaining = last_feature - col;
+
+        match rng.random_range(0..3) {
+            // Gap, then somewhere to land
+            0 if remaining >= 4 => {
+                let next_height = pick_height(&mut rng, config, height);
+                let rise = next_height as i32 - height as i32;
+                // Nothing this wide can be jumped, so it becomes a plain step
+                let width = match config.max_gap(rise) {
+                    0 => 0,
+                    max_gap => rng.random_range(1..=max_gap).min(remaining - 3),
+                };
+
+                col += width;
+                height = next_height;
+                fill_ground(&mut grid, col..col + 3, height);
+                col += 3;
+                since_checkpoint += width + 3;
+            }
+            // Step up or down
+            1 if remaining >= 3 => {
+                height = pick_height(&mut rng, config, height);
+                let run = rng.random_range(2..=4).min(remaining);
+                fill_ground(&mut grid, col..col + run, height);
+                col += run;
+                since_checkpoint += run;
+            }
+            // Flat run, with whatever is on it
+            _ => {
+                let run = rng.random_range(3..=7).min(remaining);
+                fill_ground(&mut grid, col..col + run, height);
+
+                if since_checkpoint >= CHECKPOINT_SPACING && config.fits(height) {
+                    grid[height][col] = Tile::Checkpoint;
+                    since_checkpoint = 0;
+                }
+
+                // Single spikes stay off the first and last two tiles of a run,
+                // so there is always room to jump them like a one tile gap
+                let hazard = (run >= 5
+                    && config.max_gap(0) >= 1
+                    && config.fits(height)
+                    && rng.random_bool(config.hazard_chance))
+                .then(|| rng.random_range(col + 2..col + run - 2));
+                if let Some(hazard_col) = hazard {
+                    grid[height][hazard_col] = Tile::Hazard;
+                }
+
+                // Two above the ground still leaves room to walk under
+                let platform_row = height + 2;
+                if hazard.is_none()
+                    && run >= 4
+                    && config.fits(platform_row + 1)
+                    && rng.random_bool(config.platform_chance)
+                {
+                    let len = rng.random_range(2..=3).min(run - 1);
+                    let platform_cols = col + 1..col + 1 + len;
+                    for (platform_col, tile) in grid[platform_row]
+                        .iter_mut()
+                        .enumerate()
+                        .skip(platform_cols.start)
+                        .take(len)
+                    {
+                        *tile = Tile::Brick(platform_col % 4);
+                    }
+                    grid[platform_row + 1][platform_cols].fill(Tile::Collectible);
+                } else if config.fits(height + 1) && rng.random_bool(config.collectible_chance) {
+                    let collectible_col = rng.random_range(col..col + run);
+                    grid[height + 1][collectible_col] = Tile::Collectible;
+                }
+
+                col += run;
+                since_checkpoint += run;
+            }
+        }
+    }
+
+    fill_ground(&mut grid, col..config.columns, height);
+
+    LevelData {
+        name: format!("Generated #{}", seed),
+        music: None,
+        npc_dialogue: Vec::new(),
+        waves: None,
+        triggers: vec![goal(config)],
+        tiles: grid
+            .iter()
+            .rev()
+            .map(|row| row.iter().map(|tile| tile.to_char()).collect())
+            .collect(),
+    }
+}
+
+/// The last column, from the ground to the top
+fn goal(config: &LevelGenConfig) -> TriggerData {
+    TriggerData {
+        at: (config.columns as f32 - 1., (config.rows as f32 - 1.) / 2.),
+        shape: TriggerShape::Box(1., config.rows as f32),
+        action: TriggerAction::Goal,
+    }
+}
+
+/// A ground height that can be jumped to from `height`
+fn pick_height(rng: &mut ChaCha8Rng, config: &LevelGenConfig, height: usize) -> usize {
+    let highest = config.max_height().min(height + config.max_rise());
+    rng.random_range(1..=highest)
+}
+
+fn fill_ground(grid: &mut [Vec<Tile>], cols: std::ops::Range<usize>, height: usize) {
+    for col in cols {
+        for row in grid.iter_mut().take(height) {
+            row[col] = Tile::Brick(col % 4);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Height of the solid ground in each column, ignoring floating platforms
+    fn ground(level: &LevelData) -> Vec<usize> {
+        (0..level.columns())
+            .map(|col| {
+                (0..level.rows())
+                    .take_while(|&row| matches!(level.tile(col, row), Tile::Brick(_)))
+                    .count()
+            })
+            .collect()
+    }
+
+    /// Whether `col` is ground at `height` that is safe to stand on
+    fn safe(level: &LevelData, ground: &[usize], col: usize, height: usize) -> bool {
+        ground.get(col) == Some(&height) && level.tile(col, height) != Tile::Hazard
+    }
+
+    #[test]
+    fn same_seed_same_level() {
+        let config = LevelGenConfig::default();
+        for seed in 0..20 {
+            assert_eq!(generate(seed, &config).tiles, generate(seed, &config).tiles);
+        }
+        assert_ne!(generate(1, &config).tiles, generate(2, &config).tiles);
+    }
+
+    /// Walks every seed's level checking each jump against `config`
+    fn assert_makeable(config: &LevelGenConfig, seeds: std::ops::Range<u64>) {
+        for seed in seeds {
+            let level = generate(seed, config);
+            let ground = ground(&level);
+            assert_eq!(ground.len(), config.columns);
+            assert!(safe(&level, &ground, 0, ground[0]), "seed {}", seed);
+
+            let mut col = 0;
+            while col + 1 < ground.len() {
+                let height = ground[col];
+                let next = col + 1;
+
+                if ground[next] > 0 && level.tile(next, ground[next]) != Tile::Hazard {
+                    // Walking on, or stepping up
+                    let rise = ground[next] as i32 - height as i32;
+                    assert!(
+                        rise <= config.max_rise() as i32,
+                        "seed {} col {}",
+                        seed,
+                        col
+                    );
+                    if rise > 0 {
+                        assert!(col >= 1 && safe(&level, &ground, col - 1, height));
+                        assert!(safe(&level, &ground, next + 1, ground[next]));
+                    }
+                    col = next;
+                    continue;
+                }
+
+                // A gap or a spike, find where it lands
+                let land = (next..ground.len())
+                    .find(|&c| ground[c] > 0 && level.tile(c, ground[c]) != Tile::Hazard)
+                    .unwrap_or_else(|| panic!("seed {} has no ground after col {}", seed, col));
+                let rise = ground[land] as i32 - height as i32;
+                let width = land - next;
+                assert!(
+                    width <= config.max_gap(rise),
+                    "seed {} gap of {} at col {}",
+                    seed,
+                    width,
+                    col
+                );
+                assert!(
+                    col >= 1 && safe(&level, &ground, col - 1, height),
+                    "seed {} takes off from one tile at col {}",
+                    seed,
+                    col
+                );
+                assert!(
+                    safe(&level, &ground, land + 1, ground[land]),
+                    "seed {} lands on one tile at col {}",
+                    seed,
+                    land
+                );
+                col = land;
+            }
+        }
+    }
+
+    #[test]
+    fn every_jump_is_makeable() {
+        assert_makeable(&LevelGenConfig::default(), 0..500);
+    }
+
+    #[test]
+    fn short_levels_stay_in_bounds() {
+        for rows in 2..=4 {
+            let config = LevelGenConfig {
+                rows,
+                ..Default::default()
+            };
+            assert_makeable(&config, 0..100);
+        }
+    }
+
+    #[test]
+    fn weak_jumps_get_no_gaps() {
+        let config = LevelGenConfig {
+            run_speed: 1.,
+            ..Default::default()
+        };
+        assert_eq!(config.max_gap(0), 0);
+        assert_makeable(&config, 0..100);
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::{asset::LoadState, prelude::*};
+use bevy::prelude::*;
 
 use crate::{GameState, PROGRESS_FRAME, PROGRESS_HEIGHT, PROGRESS_LENGTH};
 
@@ -34,7 +34,13 @@ impl Plugin for LoadingPlugin {
     }
 }
 
-fn setup_loading(mut commands: Commands) {
+fn setup_loading(mut commands: Commands, camera: Option<Single<&mut Transform, With<Camera>>>) {
+    // The previous level may have left the camera anywhere. The first load
+    // starts before Startup has spawned the camera, which is already centred.
+    if let Some(mut camera) = camera {
+        camera.translation.x = 0.;
+    }
+
     commands.spawn((
         Sprite::from_color(Color::BLACK, Vec2::ONE),
         Transform {
@@ -1,9 +1,41 @@
-use bevy::{prelude::*, window::PresentMode};
+// Bevy queries and system parameters routinely trip these lints
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
 
+use bevy::prelude::*;
+
+use console::ConsoleExt;
+
+mod campaign;
+mod checkpoint;
+mod collectible;
+mod collision;
+mod console;
+#[cfg(all(debug_assertions, feature = "debug_overlay"))]
+mod debug_overlay;
+mod dialogue;
+mod editor;
+mod enemy;
+mod ghost;
+mod hazard;
+mod leaderboard;
 mod level;
+mod level_data;
+mod levelgen;
 mod loading;
 mod music;
+mod npc;
+mod options;
+mod particles;
 mod player;
+mod saves;
+mod settings;
+mod sfx;
+mod spatial_audio;
+mod speedrun;
+mod telemetry;
+mod timeline;
+mod trigger;
+mod viewport;
 mod win;
 
 const TITLE: &str = "Better Bevy Project Setup";
@@ -13,11 +45,11 @@ const WIN_H: f32 = 720.;
 const PLAYER_SPEED: f32 = 500.;
 const ACCEL_RATE: f32 = 5000.;
 const ANIM_TIME: f32 = 0.2;
+const GRAVITY: f32 = 2000.;
+const JUMP_SPEED: f32 = 1000.;
 
 const TILE_SIZE: f32 = 100.;
 
-const LEVEL_LEN: f32 = 5000.;
-
 const PROGRESS_LENGTH: f32 = 120.;
 const PROGRESS_HEIGHT: f32 = 20.;
 const PROGRESS_FRAME: f32 = 5.;
@@ -28,20 +60,22 @@ enum GameState {
     Loading,
     Playing,
     Win,
+    /// Developer mode for building levels, toggled from Playing with F1
+    Editor,
 }
 
 fn main() {
-    App::new()
+    // Needed before the window exists, so it opens the way it was left
+    let (settings, settings_problem) = settings::Settings::load();
+
+    let mut app = App::new();
+    app
         // Setup Bevy and game window
         .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: String::from(TITLE),
-                resolution: (WIN_W, WIN_H).into(),
-                present_mode: PresentMode::AutoVsync,
-                ..default()
-            }),
+            primary_window: Some(settings.window()),
             ..default()
         }))
+        .insert_resource(settings)
         .insert_resource(ClearColor(Color::Srgba(Srgba::gray(0.25))))
         // Set initial state
         .init_state::<GameState>()
@@ -50,22 +84,77 @@ fn main() {
         .add_systems(OnEnter(GameState::Loading), log_state_change)
         .add_systems(OnEnter(GameState::Playing), log_state_change)
         .add_systems(OnEnter(GameState::Win), log_state_change)
+        .add_systems(OnEnter(GameState::Editor), log_state_change)
+        .add_console_command("state", "state <name> - switch GameState", set_state)
         // Add all subsystems
         .add_plugins((
             loading::LoadingPlugin,
-            music::BackgroundMusicPlugin,
+            campaign::CampaignPlugin,
+            checkpoint::CheckpointPlugin,
+            hazard::HazardPlugin,
+            collectible::CollectiblePlugin,
+            enemy::EnemyPlugin,
+            music::MusicPlugin,
             player::PlayerPlugin,
             level::LevelPlugin,
             win::WinPlugin,
+            editor::EditorPlugin,
+            console::ConsolePlugin,
+            viewport::ViewportPlugin,
+            settings::SettingsPlugin,
+            options::OptionsPlugin,
         ))
-        // Run the game
-        .run();
+        .add_plugins((
+            saves::SavesPlugin,
+            sfx::SfxPlugin,
+            spatial_audio::SpatialAudioPlugin,
+            particles::ParticlePlugin,
+            dialogue::DialoguePlugin,
+            npc::NpcPlugin,
+            trigger::TriggerPlugin,
+            telemetry::TelemetryPlugin,
+            timeline::TimelinePlugin,
+            speedrun::SpeedrunPlugin,
+            ghost::GhostPlugin,
+            leaderboard::LeaderboardPlugin,
+        ));
+
+    #[cfg(all(debug_assertions, feature = "debug_overlay"))]
+    app.add_plugins(debug_overlay::DebugOverlayPlugin);
+
+    // Only now that logging is set up can anyone see this
+    if let Some(problem) = settings_problem {
+        warn!("{}", problem);
+    }
+
+    // Run the game
+    app.run();
 }
 
-fn setup_camera(mut commands: Commands) {
-    commands.spawn(Camera2d);
+fn setup_camera(mut commands: Commands, virtual_viewport: Res<viewport::VirtualViewport>) {
+    commands.spawn((
+        Camera2d,
+        viewport::virtual_projection(&virtual_viewport),
+        spatial_audio::listener(&virtual_viewport),
+    ));
 }
 
 fn log_state_change(state: Res<State<GameState>>) {
     info!("Just moved to {:?}!", state.get());
 }
+
+fn set_state(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let name = args.first().ok_or("which state?")?;
+    let state = [
+        GameState::Loading,
+        GameState::Playing,
+        GameState::Win,
+        GameState::Editor,
+    ]
+    .into_iter()
+    .find(|state| format!("{:?}", state).eq_ignore_ascii_case(name))
+    .ok_or_else(|| format!("no state called '{}'", name))?;
+
+    world.resource_mut::<NextState<GameState>>().set(state);
+    Ok(format!("moving to {:?}", state))
+}
@@ -1,42 +1,138 @@
-use bevy::prelude::*;
+use bevy::{audio::Volume, prelude::*};
 
 use crate::{
-    GameState,
-    loading::{LoadingAssets, despawn_with},
+    GameState, level::LevelHandle, level_data::LevelData, loading::LoadingAssets,
+    settings::Settings,
 };
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct BackgroundMusic(Handle<AudioSource>);
+/// Played by any level that doesn't name its own track
+const DEFAULT_TRACK: &str = "bg_music.ogg";
+/// Seconds for one track to fade out while the next fades in
+const CROSSFADE_TIME: f32 = 1.5;
+/// Music volume while the Win screen is up
+const DUCK_VOLUME: f32 = 0.3;
+/// How quickly ducking comes and goes, in volume per second
+const DUCK_RATE: f32 = 2.;
 
+/// Decides what should be playing; `direct_music` makes it so
+#[derive(Resource)]
+struct MusicDirector {
+    /// Track that should be playing, relative to the assets folder
+    track: Option<String>,
+    /// Multiplier eased towards `DUCK_VOLUME` during the Win screen
+    duck: f32,
+}
+
+/// Track set by a music trigger, played instead of the level's own until the
+/// next level loads
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct MusicOverride(Option<String>);
+
+/// One playing track, faded in or out as the director changes its mind
 #[derive(Component)]
-pub struct Music;
+struct MusicTrack {
+    path: String,
+    fade: f32,
+}
 
-pub struct BackgroundMusicPlugin;
-impl Plugin for BackgroundMusicPlugin {
+pub struct MusicPlugin;
+impl Plugin for MusicPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, load_background_music)
-            .add_systems(OnEnter(GameState::Playing), start_background_music)
-            .add_systems(OnExit(GameState::Playing), despawn_with::<Music>);
+        app.insert_resource(MusicDirector {
+            track: None,
+            duck: 1.,
+        })
+        .init_resource::<MusicOverride>()
+        .add_systems(Startup, load_default_track)
+        .add_systems(OnEnter(GameState::Loading), clear_override)
+        .add_systems(Update, (choose_track, direct_music).chain());
+    }
+}
+
+fn load_default_track(asset_server: Res<AssetServer>, mut loading_assets: ResMut<LoadingAssets>) {
+    let handle: Handle<AudioSource> = asset_server.load(DEFAULT_TRACK);
+    loading_assets.push(handle.untyped());
+}
+
+fn clear_override(mut music_override: ResMut<MusicOverride>) {
+    **music_override = None;
+}
+
+/// Each level plays its own track, or the default one, unless a trigger has
+/// changed it; the Win screen keeps
+/// the last level's music but quieter, and loading keeps whatever was on
+fn choose_track(
+    state: Res<State<GameState>>,
+    level_handle: Option<Res<LevelHandle>>,
+    levels: Res<Assets<LevelData>>,
+    music_override: Res<MusicOverride>,
+    mut director: ResMut<MusicDirector>,
+) {
+    if matches!(state.get(), GameState::Playing | GameState::Editor) {
+        let level_music = level_handle
+            .and_then(|handle| levels.get(&**handle))
+            .and_then(|level| level.music.clone());
+        let track = music_override
+            .clone()
+            .or(level_music)
+            .unwrap_or_else(|| DEFAULT_TRACK.to_string());
+        if director.track.as_ref() != Some(&track) {
+            info!("Music: {}", track);
+            director.track = Some(track);
+        }
     }
 }
 
-fn load_background_music(
+fn direct_music(
     mut commands: Commands,
+    time: Res<Time<Real>>,
     asset_server: Res<AssetServer>,
-    mut loading_assets: ResMut<LoadingAssets>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    mut director: ResMut<MusicDirector>,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&mut AudioSink>)>,
 ) {
-    let bg_music_handle = asset_server.load("bg_music.ogg");
-    loading_assets.push(bg_music_handle.clone().untyped());
-    commands.insert_resource(BackgroundMusic(bg_music_handle));
-}
+    let dt = time.delta_secs();
+
+    let duck_target = if *state.get() == GameState::Win {
+        DUCK_VOLUME
+    } else {
+        1.
+    };
+    director.duck =
+        director.duck + (duck_target - director.duck).clamp(-DUCK_RATE * dt, DUCK_RATE * dt);
 
-fn start_background_music(mut commands: Commands, background_music: Res<BackgroundMusic>) {
-    commands.spawn((
-        AudioPlayer(background_music.clone()),
-        PlaybackSettings {
-            mode: bevy::audio::PlaybackMode::Loop,
-            ..default()
-        },
-        Music,
-    ));
+    let wanted = director.track.as_deref();
+    if let Some(path) = wanted
+        && !tracks.iter().any(|(_, track, _)| track.path == path)
+    {
+        commands.spawn((
+            AudioPlayer::new(asset_server.load(path.to_string())),
+            PlaybackSettings::LOOP.with_volume(Volume::SILENT),
+            MusicTrack {
+                path: path.to_string(),
+                fade: 0.,
+            },
+        ));
+    }
+
+    let step = dt / CROSSFADE_TIME;
+    for (entity, mut track, sink) in tracks.iter_mut() {
+        if Some(track.path.as_str()) == wanted {
+            track.fade = (track.fade + step).min(1.);
+        } else {
+            track.fade -= step;
+            if track.fade <= 0. {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        }
+
+        // The sink only exists once the track has started playing
+        if let Some(mut sink) = sink {
+            let volume =
+                settings.master_volume * settings.music_volume * track.fade * director.duck;
+            sink.set_volume(Volume::Linear(volume));
+        }
+    }
 }
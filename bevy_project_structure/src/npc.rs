@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState, TILE_SIZE,
+    dialogue::{ActiveDialogue, Dialogue},
+    loading::despawn_with,
+    player::{Player, PlayerSheet},
+};
+
+const NPC_COLOR: Color = Color::srgb(0.6, 1., 0.7);
+/// How close the player has to be to talk
+const TALK_RANGE: f32 = TILE_SIZE * 1.5;
+
+/// Someone to talk to
+#[derive(Component)]
+pub struct Npc {
+    pub dialogue: Handle<Dialogue>,
+}
+
+impl Npc {
+    pub fn in_reach(&self, transform: &Transform, player: Vec2) -> bool {
+        transform.translation.truncate().distance(player) <= TALK_RANGE
+    }
+}
+
+/// Floats over an NPC while the player is close enough to talk
+#[derive(Component)]
+struct TalkPrompt;
+
+pub struct NpcPlugin;
+impl Plugin for NpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, show_prompts.run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), despawn_with::<Npc>);
+    }
+}
+
+/// NPCs borrow the player's sprite, tinted so they stand out
+pub fn spawn_npc(
+    commands: &mut Commands,
+    player_sheet: &PlayerSheet,
+    dialogue: Handle<Dialogue>,
+    position: Vec2,
+) {
+    let mut sprite = Sprite::from_atlas_image(
+        player_sheet.0.clone(),
+        TextureAtlas {
+            layout: player_sheet.1.clone(),
+            index: 0,
+        },
+    );
+    sprite.color = NPC_COLOR;
+    // Face back towards where the player starts
+    sprite.flip_x = true;
+
+    commands
+        .spawn((
+            sprite,
+            Transform::from_translation(position.extend(3.)),
+            Npc { dialogue },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text2d::new("E"),
+                TextFont {
+                    font_size: 24.,
+                    ..default()
+                },
+                Transform::from_xyz(0., TILE_SIZE * 0.75, 1.),
+                Visibility::Hidden,
+                TalkPrompt,
+            ));
+        });
+}
+
+fn show_prompts(
+    active: Res<ActiveDialogue>,
+    player: Single<&Transform, With<Player>>,
+    npcs: Query<(&Transform, &Npc, &Children), Without<Player>>,
+    mut prompts: Query<&mut Visibility, With<TalkPrompt>>,
+) {
+    let player = player.translation.truncate();
+    for (transform, npc, children) in &npcs {
+        let visible = active.is_none() && npc.in_reach(transform, player);
+        for child in children {
+            if let Ok(mut visibility) = prompts.get_mut(*child) {
+                *visibility = if visible {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+            }
+        }
+    }
+}
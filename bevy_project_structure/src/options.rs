@@ -0,0 +1,209 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{
+    console::ConsoleSystems,
+    leaderboard::ShowLeaderboard,
+    settings::{DisplayMode, Settings, Vsync, WINDOW_SCALES},
+};
+
+const TOGGLE_KEY: KeyCode = KeyCode::Escape;
+const ROWS: usize = 7;
+/// Row that opens the leaderboard rather than changing a setting
+const LEADERBOARD_ROW: usize = 6;
+/// Change in volume for each press of left or right
+const VOLUME_STEP: f32 = 0.1;
+const SELECTED_COLOR: Color = Color::srgb(1., 0.85, 0.1);
+
+/// Systems that read the keyboard for the options menu, and hide it from
+/// gameplay while the menu is open
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OptionsSystems;
+
+#[derive(Resource, Default)]
+struct OptionsMenu {
+    open: bool,
+    /// Row the arrow keys are changing
+    selected: usize,
+}
+
+#[derive(Component)]
+struct OptionsRoot;
+
+#[derive(Component)]
+struct OptionsRow(usize);
+
+pub struct OptionsPlugin;
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OptionsMenu>()
+            .add_systems(Startup, setup_options)
+            // Like the console, take the keyboard before gameplay sees it
+            .add_systems(
+                PreUpdate,
+                navigate_options
+                    .in_set(OptionsSystems)
+                    .after(InputSystem)
+                    .after(ConsoleSystems),
+            )
+            .add_systems(Update, update_options);
+    }
+}
+
+fn setup_options(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.7)),
+            Visibility::Hidden,
+            GlobalZIndex(40),
+            OptionsRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("OPTIONS"),
+                TextFont {
+                    font_size: 40.,
+                    ..default()
+                },
+            ));
+            for row in 0..ROWS {
+                parent.spawn((
+                    Text::default(),
+                    TextFont {
+                        font_size: 24.,
+                        ..default()
+                    },
+                    OptionsRow(row),
+                ));
+            }
+            parent.spawn((
+                Text::new("Up/Down choose, Left/Right change, Esc close"),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn navigate_options(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut menu: ResMut<OptionsMenu>,
+    mut settings: ResMut<Settings>,
+    mut time: ResMut<Time<Virtual>>,
+    mut show_leaderboard: EventWriter<ShowLeaderboard>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        menu.open = !menu.open;
+        // The game waits while the menu is up
+        if menu.open {
+            time.pause();
+        } else {
+            time.unpause();
+        }
+        keys.reset_all();
+        return;
+    }
+
+    if !menu.open {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        menu.selected = (menu.selected + ROWS - 1) % ROWS;
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        menu.selected = (menu.selected + 1) % ROWS;
+    }
+
+    if menu.selected == LEADERBOARD_ROW
+        && (keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::ArrowRight))
+    {
+        // The game stays paused behind the leaderboard
+        menu.open = false;
+        show_leaderboard.write(ShowLeaderboard);
+        keys.reset_all();
+        return;
+    }
+
+    let step = if keys.just_pressed(KeyCode::ArrowRight) {
+        1
+    } else if keys.just_pressed(KeyCode::ArrowLeft) {
+        -1
+    } else {
+        0
+    };
+    if step != 0 {
+        match menu.selected {
+            0 => settings.display_mode = cycle(&DisplayMode::ALL, settings.display_mode, step),
+            1 => settings.vsync = cycle(&Vsync::ALL, settings.vsync, step),
+            2 => settings.window_scale = cycle(&WINDOW_SCALES, settings.window_scale, step),
+            3 => nudge_volume(&mut settings.master_volume, step),
+            4 => nudge_volume(&mut settings.music_volume, step),
+            5 => nudge_volume(&mut settings.sfx_volume, step),
+            _ => {}
+        }
+    }
+
+    // Keep the game from reacting to menu keys
+    keys.reset_all();
+}
+
+/// The entry `step` places away from `current`, wrapping around
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, step: isize) -> T {
+    let index = all.iter().position(|v| *v == current).unwrap_or(0);
+    all[(index as isize + step).rem_euclid(all.len() as isize) as usize]
+}
+
+/// Volumes stop at either end rather than wrapping
+fn nudge_volume(volume: &mut f32, step: isize) {
+    // Rounded so repeated steps don't drift away from tenths
+    *volume = ((*volume + step as f32 * VOLUME_STEP).clamp(0., 1.) * 10.).round() / 10.;
+}
+
+fn update_options(
+    menu: Res<OptionsMenu>,
+    settings: Res<Settings>,
+    mut root: Single<&mut Visibility, With<OptionsRoot>>,
+    mut rows: Query<(&OptionsRow, &mut Text, &mut TextColor)>,
+) {
+    if !menu.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    **root = if menu.open {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+
+    for (row, mut text, mut color) in rows.iter_mut() {
+        text.0 = match row.0 {
+            0 => format!("Display: < {:?} >", settings.display_mode),
+            1 => format!("Vsync: < {:?} >", settings.vsync),
+            2 => format!("Window scale: < {}x >", settings.window_scale),
+            3 => volume_row("Master volume", settings.master_volume),
+            4 => volume_row("Music volume", settings.music_volume),
+            5 => volume_row("SFX volume", settings.sfx_volume),
+            _ => String::from("Leaderboard >"),
+        };
+        color.0 = if row.0 == menu.selected {
+            SELECTED_COLOR
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+fn volume_row(label: &str, volume: f32) -> String {
+    format!("{}: < {:.0}% >", label, volume * 100.)
+}
@@ -0,0 +1,346 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    color::Mix,
+    prelude::*,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    GameState,
+    collectible::Collected,
+    loading::{LoadingAssets, despawn_with},
+    player::{Grounded, PLAYER_HALF_SIZE, Player, PlayerDied, Velocity},
+};
+
+/// Particles draw in front of the level but behind the player
+const PARTICLE_Z: f32 = 800.;
+
+/// How an emitter spawns particles and how they change over their life,
+/// stored as RON in `assets/particles/`
+///
+/// Pairs are either a range a value is picked from for each particle, or a
+/// particle's value at birth and at death.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct ParticleEffect {
+    /// Particles spawned as soon as the emitter appears
+    #[serde(default)]
+    pub burst: u32,
+    /// Particles per second after that, for `duration` seconds
+    #[serde(default)]
+    pub rate: f32,
+    #[serde(default)]
+    pub duration: f32,
+    /// Seconds each particle lives
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    /// Degrees counterclockwise from the right that particles head in
+    pub direction: f32,
+    /// Degrees either side of `direction` particles may stray
+    pub spread: f32,
+    /// Downwards acceleration; negative floats up
+    #[serde(default)]
+    pub gravity: f32,
+    /// sRGBA at birth and death
+    pub color: ([f32; 4], [f32; 4]),
+    /// Width and height in pixels at birth and death
+    pub size: (f32, f32),
+    /// Draw frames of a sprite sheet instead of plain squares
+    #[serde(default)]
+    pub atlas: Option<ParticleAtlas>,
+    #[serde(skip)]
+    sheet: Option<(Handle<Image>, Handle<TextureAtlasLayout>)>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ParticleAtlas {
+    /// Path relative to the assets folder
+    pub image: String,
+    pub tile_size: (u32, u32),
+    pub columns: u32,
+    pub rows: u32,
+    /// Frames shown in turn over each particle's life
+    pub frames: Vec<usize>,
+}
+
+#[derive(Default)]
+pub struct ParticleEffectLoader;
+
+impl AssetLoader for ParticleEffectLoader {
+    type Asset = ParticleEffect;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut effect: ParticleEffect = ron::de::from_bytes(&bytes)?;
+
+        if let Some(atlas) = &effect.atlas {
+            let image = load_context.load(&atlas.image);
+            let layout = load_context.add_labeled_asset(
+                String::from("layout"),
+                TextureAtlasLayout::from_grid(
+                    UVec2::new(atlas.tile_size.0, atlas.tile_size.1),
+                    atlas.columns,
+                    atlas.rows,
+                    None,
+                    None,
+                ),
+            );
+            effect.sheet = Some((image, layout));
+        }
+        Ok(effect)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["particles.ron"]
+    }
+}
+
+/// Effects the game spawns itself
+#[derive(Resource)]
+pub struct ParticleEffects {
+    pub dust: Handle<ParticleEffect>,
+    pub sparks: Handle<ParticleEffect>,
+    pub pickup: Handle<ParticleEffect>,
+}
+
+/// Spawns particles of `effect` from its position until the effect's
+/// duration is up
+#[derive(Component)]
+pub struct ParticleEmitter {
+    effect: Handle<ParticleEffect>,
+    age: f32,
+    /// Fractional particles owed by `rate`, carried between frames
+    owed: f32,
+    burst_done: bool,
+}
+
+#[derive(Component)]
+struct Particle {
+    effect: Handle<ParticleEffect>,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+pub struct ParticlePlugin;
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ParticleEffect>()
+            .init_asset_loader::<ParticleEffectLoader>()
+            .add_systems(Startup, load_effects)
+            .add_systems(
+                Update,
+                (
+                    dust_on_turn,
+                    sparks_on_damage,
+                    burst_on_pickup,
+                    emit,
+                    update_particles,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                OnExit(GameState::Playing),
+                (despawn_with::<ParticleEmitter>, despawn_with::<Particle>),
+            );
+    }
+}
+
+fn load_effects(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    let effects = ParticleEffects {
+        dust: asset_server.load("particles/dust.particles.ron"),
+        sparks: asset_server.load("particles/sparks.particles.ron"),
+        pickup: asset_server.load("particles/pickup.particles.ron"),
+    };
+    for handle in [&effects.dust, &effects.sparks, &effects.pickup] {
+        loading_assets.push(handle.clone().untyped());
+    }
+    commands.insert_resource(effects);
+}
+
+pub fn spawn_emitter(commands: &mut Commands, effect: &Handle<ParticleEffect>, position: Vec2) {
+    commands.spawn((
+        Transform::from_translation(position.extend(PARTICLE_Z)),
+        ParticleEmitter {
+            effect: effect.clone(),
+            age: 0.,
+            owed: 0.,
+            burst_done: false,
+        },
+    ));
+}
+
+/// Puff of dust at the player's feet when they change direction on the ground
+fn dust_on_turn(
+    mut commands: Commands,
+    effects: Res<ParticleEffects>,
+    player: Single<(&Transform, &Velocity, &Grounded), With<Player>>,
+    mut last_dir: Local<f32>,
+) {
+    let (transform, velocity, grounded) = player.into_inner();
+    if velocity.x == 0. {
+        return;
+    }
+
+    let dir = velocity.x.signum();
+    if **grounded && *last_dir != 0. && dir != *last_dir {
+        let feet = transform.translation.truncate() - Vec2::new(0., PLAYER_HALF_SIZE.y);
+        spawn_emitter(&mut commands, &effects.dust, feet);
+    }
+    *last_dir = dir;
+}
+
+fn sparks_on_damage(
+    mut commands: Commands,
+    effects: Res<ParticleEffects>,
+    mut died: EventReader<PlayerDied>,
+) {
+    for event in died.read() {
+        spawn_emitter(&mut commands, &effects.sparks, event.position);
+    }
+}
+
+fn burst_on_pickup(
+    mut commands: Commands,
+    effects: Res<ParticleEffects>,
+    mut collected: EventReader<Collected>,
+) {
+    for event in collected.read() {
+        spawn_emitter(&mut commands, &effects.pickup, event.position);
+    }
+}
+
+fn emit(
+    mut commands: Commands,
+    time: Res<Time>,
+    effects: Res<Assets<ParticleEffect>>,
+    mut emitters: Query<(Entity, &Transform, &mut ParticleEmitter)>,
+) {
+    let mut rng = rand::rng();
+
+    for (entity, transform, mut emitter) in emitters.iter_mut() {
+        let Some(effect) = effects.get(&emitter.effect) else {
+            continue;
+        };
+
+        let mut count = 0;
+        if !emitter.burst_done {
+            count += effect.burst;
+            emitter.burst_done = true;
+        }
+        if emitter.age < effect.duration {
+            emitter.owed += effect.rate * time.delta_secs();
+            count += emitter.owed as u32;
+            emitter.owed = emitter.owed.fract();
+        }
+
+        for _ in 0..count {
+            let angle =
+                (effect.direction + rng.random_range(-1.0..=1.0) * effect.spread).to_radians();
+            let speed = random_between(&mut rng, effect.speed);
+            let sprite = match &effect.sheet {
+                Some((image, layout)) => Sprite::from_atlas_image(
+                    image.clone(),
+                    TextureAtlas {
+                        layout: layout.clone(),
+                        index: first_frame(effect),
+                    },
+                ),
+                None => Sprite::default(),
+            };
+
+            commands.spawn((
+                Sprite {
+                    color: srgba(effect.color.0),
+                    custom_size: Some(Vec2::splat(effect.size.0)),
+                    ..sprite
+                },
+                *transform,
+                Particle {
+                    effect: emitter.effect.clone(),
+                    velocity: Vec2::from_angle(angle) * speed,
+                    age: 0.,
+                    lifetime: random_between(&mut rng, effect.lifetime),
+                },
+            ));
+        }
+
+        emitter.age += time.delta_secs();
+        if emitter.age >= effect.duration {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    effects: Res<Assets<ParticleEffect>>,
+    mut particles: Query<(Entity, &mut Transform, &mut Sprite, &mut Particle)>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut transform, mut sprite, mut particle) in particles.iter_mut() {
+        particle.age += dt;
+        let Some(effect) = effects.get(&particle.effect) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y -= effect.gravity * dt;
+        transform.translation += (particle.velocity * dt).extend(0.);
+
+        let life = particle.age / particle.lifetime;
+        let (start, end) = effect.color;
+        sprite.color = LinearRgba::from(srgba(start))
+            .mix(&LinearRgba::from(srgba(end)), life)
+            .into();
+        sprite.custom_size = Some(Vec2::splat(effect.size.0.lerp(effect.size.1, life)));
+
+        if let (Some(atlas), Some(frames)) = (
+            &mut sprite.texture_atlas,
+            effect.atlas.as_ref().map(|atlas| &atlas.frames),
+        ) && !frames.is_empty()
+        {
+            let frame = ((life * frames.len() as f32) as usize).min(frames.len() - 1);
+            atlas.index = frames[frame];
+        }
+    }
+}
+
+fn random_between(rng: &mut impl Rng, (low, high): (f32, f32)) -> f32 {
+    if high > low {
+        rng.random_range(low..high)
+    } else {
+        low
+    }
+}
+
+fn srgba([r, g, b, a]: [f32; 4]) -> Color {
+    Color::srgba(r, g, b, a)
+}
+
+fn first_frame(effect: &ParticleEffect) -> usize {
+    effect
+        .atlas
+        .as_ref()
+        .and_then(|atlas| atlas.frames.first().copied())
+        .unwrap_or(0)
+}
@@ -2,10 +2,15 @@ use bevy::prelude::*;
 use std::convert::From;
 
 use crate::{
-    ACCEL_RATE, ANIM_TIME, GameState, LEVEL_LEN, PLAYER_SPEED, TILE_SIZE, WIN_H, WIN_W,
-    level::Background,
+    ACCEL_RATE, ANIM_TIME, GRAVITY, GameState, JUMP_SPEED, PLAYER_SPEED, TILE_SIZE,
+    checkpoint::ActiveCheckpoint,
+    collision::{Collider, Solid, overlaps},
+    console::{ConsoleExt, parse_arg},
+    dialogue::dialogue_closed,
+    level::{Background, LevelLength},
     loading::{LoadingAssets, despawn_with},
-    win::Win,
+    trigger::CameraLock,
+    viewport::VirtualViewport,
 };
 
 #[derive(Component)]
@@ -20,8 +25,36 @@ pub struct AnimationFrameCount(usize);
 #[derive(Component, Deref, DerefMut)]
 pub struct Velocity(Vec2);
 
+/// Top running speed, adjustable from the console
+#[derive(Resource, Deref, DerefMut)]
+pub struct PlayerSpeed(f32);
+
+/// Whether the player is standing on something and so is allowed to jump
+#[derive(Component, Deref, DerefMut)]
+pub struct Grounded(bool);
+
+/// Sent whenever the player should go back to the last checkpoint
+#[derive(Event)]
+pub struct PlayerDied {
+    /// Where the player was when they died
+    pub position: Vec2,
+}
+
+#[derive(Event, Default)]
+pub struct PlayerJumped;
+
+/// Sent on the first frame the player is standing after being in the air
+#[derive(Event, Default)]
+pub struct PlayerLanded;
+
+/// Sent when the walking animation moves on to `frame`
+#[derive(Event)]
+pub struct PlayerAnimationFrame {
+    pub frame: usize,
+}
+
 #[derive(Resource)]
-pub struct PlayerSheet(Handle<Image>, Handle<TextureAtlasLayout>);
+pub struct PlayerSheet(pub Handle<Image>, pub Handle<TextureAtlasLayout>);
 
 impl Velocity {
     fn new() -> Self {
@@ -35,19 +68,52 @@ impl From<Vec2> for Velocity {
     }
 }
 
+/// Where the player starts a level, and respawns if no checkpoint is active
+fn player_start(viewport: &VirtualViewport) -> Vec2 {
+    Vec2::new(0., viewport.min().y + TILE_SIZE * 1.5)
+}
+
+/// The walking sprite doesn't fill its whole frame, so collide with a narrower box
+pub const PLAYER_HALF_SIZE: Vec2 = Vec2::new(TILE_SIZE * 0.3, TILE_SIZE / 2.);
+
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, load_player_sheet)
+        app.insert_resource(PlayerSpeed(PLAYER_SPEED))
+            .add_systems(Startup, load_player_sheet)
             .add_systems(OnEnter(GameState::Playing), spawn_player)
-            .add_systems(Update, move_player.run_if(in_state(GameState::Playing)))
+            // Stand still while talking
             .add_systems(
                 Update,
-                (animate_player, move_camera)
+                move_player
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(dialogue_closed),
+            )
+            .add_systems(
+                Update,
+                (check_fall, respawn_player)
+                    .chain()
                     .after(move_player)
                     .run_if(in_state(GameState::Playing)),
             )
-            .add_systems(OnExit(GameState::Playing), despawn_with::<Player>);
+            .add_systems(
+                Update,
+                (animate_player, move_camera)
+                    .after(respawn_player)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_with::<Player>)
+            .add_event::<PlayerDied>()
+            .add_event::<PlayerJumped>()
+            .add_event::<PlayerLanded>()
+            .add_event::<PlayerAnimationFrame>()
+            .add_console_command("teleport", "teleport <x> <y> - move the player", teleport)
+            .add_console_command(
+                "set player_speed",
+                "set player_speed <speed> - change top running speed",
+                set_player_speed,
+            )
+            .add_console_command("kill", "kill - respawn at the last checkpoint", kill);
     }
 }
 
@@ -71,6 +137,8 @@ fn spawn_player(
     mut commands: Commands,
     texture_atlases: Res<Assets<TextureAtlasLayout>>,
     player_sheet: Res<PlayerSheet>,
+    active_checkpoint: Res<ActiveCheckpoint>,
+    viewport: Res<VirtualViewport>,
 ) {
     let player_layout = texture_atlases.get(&player_sheet.1);
     let player_layout_len = player_layout.unwrap().len();
@@ -83,10 +151,17 @@ fn spawn_player(
                 index: 0,
             },
         ),
-        Transform::from_xyz(0., -(WIN_H / 2.) + (TILE_SIZE * 1.5), 900.),
+        // A checkpoint may already be active when coming back from the editor or a save
+        Transform::from_translation(
+            active_checkpoint
+                .unwrap_or_else(|| player_start(&viewport))
+                .extend(900.),
+        ),
         AnimationTimer(Timer::from_seconds(ANIM_TIME, TimerMode::Repeating)),
         AnimationFrameCount(player_layout_len),
         Velocity::new(),
+        Grounded(false),
+        Collider(PLAYER_HALF_SIZE),
         Player,
     ));
 }
@@ -94,62 +169,93 @@ fn spawn_player(
 fn move_player(
     time: Res<Time>,
     input: Res<ButtonInput<KeyCode>>,
-    player: Single<(&mut Transform, &mut Velocity), (With<Player>, Without<Background>)>,
-    mut win_event: EventWriter<Win>,
+    player_speed: Res<PlayerSpeed>,
+    level_length: Res<LevelLength>,
+    viewport: Res<VirtualViewport>,
+    player: Single<
+        (&mut Transform, &mut Velocity, &mut Grounded, &Collider),
+        (With<Player>, Without<Background>),
+    >,
+    solids: Query<(&Transform, &Collider), (With<Solid>, Without<Player>)>,
+    mut jumped: EventWriter<PlayerJumped>,
+    mut landed: EventWriter<PlayerLanded>,
 ) {
-    let (mut transform, mut velocity) = player.into_inner();
+    let (mut transform, mut velocity, mut grounded, collider) = player.into_inner();
 
-    let mut dir = Vec2::ZERO;
+    let mut dir = 0.;
 
     if input.pressed(KeyCode::KeyA) {
-        dir.x -= 1.;
+        dir -= 1.;
     }
 
     if input.pressed(KeyCode::KeyD) {
-        dir.x += 1.;
-    }
-
-    if input.pressed(KeyCode::KeyW) {
-        dir.y += 1.;
-    }
-
-    if input.pressed(KeyCode::KeyS) {
-        dir.y -= 1.;
+        dir += 1.;
     }
 
     let deltat = time.delta_secs();
     let accel = ACCEL_RATE * deltat;
 
-    **velocity = if dir.length() > 0. {
-        (**velocity + (dir.normalize_or_zero() * accel)).clamp_length_max(PLAYER_SPEED)
-    } else if velocity.length() > accel {
-        **velocity + (velocity.normalize_or_zero() * -accel)
+    velocity.x = if dir != 0. {
+        (velocity.x + dir * accel).clamp(-**player_speed, **player_speed)
+    } else if velocity.x.abs() > accel {
+        velocity.x - velocity.x.signum() * accel
     } else {
-        Vec2::ZERO
+        0.
     };
-    let change = **velocity * deltat;
 
-    let min = Vec3::new(
-        -WIN_W / 2. + (TILE_SIZE as f32) / 2.,
-        -WIN_H / 2. + (TILE_SIZE as f32) * 1.5,
-        900.,
-    );
-    let max = Vec3::new(
-        LEVEL_LEN - (WIN_W / 2. + (TILE_SIZE as f32) / 2.),
-        WIN_H / 2. - (TILE_SIZE as f32) / 2.,
-        900.,
-    );
+    if **grounded && (input.just_pressed(KeyCode::Space) || input.just_pressed(KeyCode::KeyW)) {
+        velocity.y = JUMP_SPEED;
+        jumped.write(PlayerJumped);
+    }
+    velocity.y -= GRAVITY * deltat;
 
-    transform.translation = (transform.translation + change.extend(0.)).clamp(min, max);
+    // Move one axis at a time so we know which way to push out of a brick
+    transform.translation.x += velocity.x * deltat;
+    for (solid_transform, solid_collider) in &solids {
+        let solid_box = solid_collider.bounds(solid_transform);
+        if overlaps(&collider.bounds(&transform), &solid_box) {
+            transform.translation.x = if velocity.x > 0. {
+                solid_box.min.x - collider.x
+            } else {
+                solid_box.max.x + collider.x
+            };
+            velocity.x = 0.;
+        }
+    }
 
-    if transform.translation.x > LEVEL_LEN - (WIN_W / 2. + TILE_SIZE) {
-        // Close enough to end of level, move to WinScreen
-        win_event.send(Win);
+    transform.translation.y += velocity.y * deltat;
+    let was_grounded = **grounded;
+    **grounded = false;
+    for (solid_transform, solid_collider) in &solids {
+        let solid_box = solid_collider.bounds(solid_transform);
+        if overlaps(&collider.bounds(&transform), &solid_box) {
+            if velocity.y < 0. {
+                transform.translation.y = solid_box.max.y + collider.y;
+                **grounded = true;
+            } else {
+                transform.translation.y = solid_box.min.y - collider.y;
+            }
+            velocity.y = 0.;
+        }
+    }
+    if **grounded && !was_grounded {
+        landed.write(PlayerLanded);
+    }
+
+    // Nothing stops the player falling into a gap, so only clamp the other sides
+    transform.translation.x = transform.translation.x.clamp(
+        viewport.min().x + collider.x,
+        **level_length + viewport.min().x - collider.x,
+    );
+    if transform.translation.y > viewport.max().y - collider.y {
+        transform.translation.y = viewport.max().y - collider.y;
+        velocity.y = velocity.y.min(0.);
     }
 }
 
 fn animate_player(
     time: Res<Time>,
+    mut frame_changed: EventWriter<PlayerAnimationFrame>,
     player: Single<
         (
             &Velocity,
@@ -164,17 +270,97 @@ fn animate_player(
     if v.cmpne(Vec2::ZERO).any() {
         timer.tick(time.delta());
 
-        if timer.just_finished() {
-            if let Some(atlas) = &mut sprite.texture_atlas {
-                atlas.index = (atlas.index + 1) % **frame_count;
-            }
+        if timer.just_finished()
+            && let Some(atlas) = &mut sprite.texture_atlas
+        {
+            atlas.index = (atlas.index + 1) % **frame_count;
+            frame_changed.write(PlayerAnimationFrame { frame: atlas.index });
         }
     }
 }
 
 fn move_camera(
+    level_length: Res<LevelLength>,
+    viewport: Res<VirtualViewport>,
+    camera_lock: Res<CameraLock>,
     player: Single<&Transform, With<Player>>,
     mut camera: Single<&mut Transform, (Without<Player>, With<Camera>)>,
 ) {
-    camera.translation.x = player.translation.x.clamp(0., LEVEL_LEN - WIN_W);
+    camera.translation.x = camera_x(
+        camera_lock.unwrap_or(player.translation.x),
+        &level_length,
+        &viewport,
+    );
+}
+
+/// Camera position following `x` without showing past either end of the
+/// level. Levels narrower than the view just stay at the start.
+fn camera_x(x: f32, level_length: &LevelLength, viewport: &VirtualViewport) -> f32 {
+    x.clamp(0., (**level_length - viewport.width()).max(0.))
+}
+
+fn check_fall(
+    viewport: Res<VirtualViewport>,
+    player: Single<&Transform, With<Player>>,
+    mut died: EventWriter<PlayerDied>,
+) {
+    if player.translation.y < viewport.min().y - TILE_SIZE {
+        died.write(PlayerDied {
+            position: player.translation.truncate(),
+        });
+    }
+}
+
+fn respawn_player(
+    mut died: EventReader<PlayerDied>,
+    active_checkpoint: Res<ActiveCheckpoint>,
+    level_length: Res<LevelLength>,
+    viewport: Res<VirtualViewport>,
+    player: Single<(&mut Transform, &mut Velocity), With<Player>>,
+    mut camera: Single<&mut Transform, (Without<Player>, With<Camera>)>,
+) {
+    if died.is_empty() {
+        return;
+    }
+    died.clear();
+
+    let (mut transform, mut velocity) = player.into_inner();
+    let respawn = active_checkpoint.unwrap_or_else(|| player_start(&viewport));
+    info!("Respawning at {}", respawn);
+
+    transform.translation = respawn.extend(transform.translation.z);
+    **velocity = Vec2::ZERO;
+    camera.translation.x = camera_x(respawn.x, &level_length, &viewport);
+}
+
+fn teleport(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let position = Vec2::new(parse_arg(args, 0)?, parse_arg(args, 1)?);
+    let (mut transform, mut velocity) = world
+        .query_filtered::<(&mut Transform, &mut Velocity), With<Player>>()
+        .single_mut(world)
+        .map_err(|_| "no player to teleport")?;
+
+    transform.translation = position.extend(transform.translation.z);
+    **velocity = Vec2::ZERO;
+    Ok(format!("teleported to {}", position))
+}
+
+fn set_player_speed(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let speed: f32 = parse_arg(args, 0)?;
+    if !speed.is_finite() || speed <= 0. {
+        return Err(format!("player_speed must be above 0, not {}", speed));
+    }
+    **world.resource_mut::<PlayerSpeed>() = speed;
+    Ok(format!("player_speed = {}", speed))
+}
+
+fn kill(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let player = world
+        .query_filtered::<&Transform, With<Player>>()
+        .single(world)
+        .map_err(|_| "no player to kill")?;
+    let position = player.translation.truncate();
+
+    world.send_event(PlayerDied { position });
+    Ok(String::from("ouch"))
 }
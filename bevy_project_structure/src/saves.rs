@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::PathBuf};
+
+use crate::{
+    GameState,
+    campaign::{CAMPAIGN, CurrentLevel, Progress},
+    checkpoint::ActiveCheckpoint,
+    console::{ConsoleExt, parse_arg},
+};
+
+pub const SLOT_COUNT: usize = 3;
+const QUICK_SAVE_KEY: KeyCode = KeyCode::F5;
+const QUICK_LOAD_KEY: KeyCode = KeyCode::F9;
+
+/// First word of every save file, so other files are never mistaken for one
+const MAGIC: &str = "PLATFORMER_SAVE";
+
+/// Version of `SaveData` written by this build
+///
+/// Whenever `SaveData` changes, bump this and add a step to `MIGRATIONS` that
+/// rewrites a save from the previous version into the new one.
+const SAVE_VERSION: u32 = 1;
+
+type Migration = fn(&str) -> Result<String, Box<dyn Error>>;
+
+/// `MIGRATIONS[n]` turns the body of a version `n + 1` save into version `n + 2`
+const MIGRATIONS: [Migration; SAVE_VERSION as usize - 1] = [];
+
+/// Everything kept in a save slot
+///
+/// On disk this is a header line, `PLATFORMER_SAVE <version> <crc32>`, then
+/// the data as RON. The checksum covers the RON, so a damaged or hand edited
+/// file is refused rather than loaded half right.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SaveData {
+    /// Index into `CAMPAIGN`
+    pub level: usize,
+    pub checkpoint: Option<(f32, f32)>,
+    pub score: u32,
+    pub lives: u32,
+    pub unlocked_levels: usize,
+    pub levels_cleared: usize,
+    pub play_time: f32,
+}
+
+impl SaveData {
+    fn capture(world: &World) -> Self {
+        let progress = world.resource::<Progress>();
+        Self {
+            level: **world.resource::<CurrentLevel>(),
+            checkpoint: world
+                .resource::<ActiveCheckpoint>()
+                .map(|position| (position.x, position.y)),
+            score: progress.score,
+            lives: progress.lives,
+            unlocked_levels: progress.unlocked_levels,
+            levels_cleared: progress.levels_cleared,
+            play_time: progress.play_time,
+        }
+    }
+
+    /// Put the game back how it was saved, through the loading screen
+    fn restore(&self, world: &mut World) {
+        **world.resource_mut::<CurrentLevel>() = self.level;
+
+        let mut progress = world.resource_mut::<Progress>();
+        progress.score = self.score;
+        progress.lives = self.lives;
+        progress.unlocked_levels = self.unlocked_levels;
+        progress.levels_cleared = self.levels_cleared;
+        progress.play_time = self.play_time;
+
+        // Loading clears the active checkpoint, so hold it until after
+        **world.resource_mut::<PendingCheckpoint>() = self.checkpoint.map(|(x, y)| Vec2::new(x, y));
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Loading);
+    }
+
+    pub fn save_file(&self, slot: usize) -> Result<(), Box<dyn Error>> {
+        let text = self.encode()?;
+        let path = slot_path(slot);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Read a slot, upgrading it from whatever version wrote it
+    ///
+    /// `Ok(None)` means the slot is empty.
+    pub fn load_file(slot: usize) -> Result<Option<Self>, Box<dyn Error>> {
+        match fs::read_to_string(slot_path(slot)) {
+            Ok(text) => Ok(Some(Self::decode(&text, &MIGRATIONS)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The whole save file, header and all
+    fn encode(&self) -> Result<String, Box<dyn Error>> {
+        let body = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        Ok(with_header(SAVE_VERSION, &body))
+    }
+
+    /// Check a save file and run its body through `migrations`
+    ///
+    /// The last migration produces the current version, so a file can be
+    /// anything from version 1 to `migrations.len() + 1`.
+    fn decode(text: &str, migrations: &[Migration]) -> Result<Self, Box<dyn Error>> {
+        let (header, body) = text.split_once('\n').ok_or("missing header")?;
+        let mut fields = header.split_whitespace();
+        if fields.next() != Some(MAGIC) {
+            return Err("not a save file".into());
+        }
+        let version: u32 = fields.next().ok_or("missing version")?.parse()?;
+        let checksum = u32::from_str_radix(fields.next().ok_or("missing checksum")?, 16)?;
+
+        if crc32fast::hash(body.as_bytes()) != checksum {
+            return Err("checksum does not match, the file is corrupted".into());
+        }
+        if version == 0 || version as usize > migrations.len() + 1 {
+            return Err(format!("unknown save version {}", version).into());
+        }
+
+        let mut body = body.to_string();
+        for migrate in &migrations[version as usize - 1..] {
+            body = migrate(&body)?;
+        }
+
+        let data: SaveData = ron::de::from_str(&body)?;
+        if data.level >= CAMPAIGN.len() {
+            return Err(format!("no level {} in the campaign", data.level + 1).into());
+        }
+        Ok(data)
+    }
+}
+
+fn with_header(version: u32, body: &str) -> String {
+    format!(
+        "{} {} {:08x}\n{}",
+        MAGIC,
+        version,
+        crc32fast::hash(body.as_bytes()),
+        body
+    )
+}
+
+/// Saves live in the OS data folder, or next to the game if there isn't one
+fn slot_path(slot: usize) -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+        .unwrap_or_default()
+        .join("saves")
+        .join(format!("slot{}.sav", slot))
+}
+
+/// Slot the quick save and quick load keys use, the last one saved or loaded
+#[derive(Resource, Deref, DerefMut)]
+struct ActiveSlot(usize);
+
+/// Checkpoint from a loaded save, applied once its level has loaded
+#[derive(Resource, Default, Deref, DerefMut)]
+struct PendingCheckpoint(Option<Vec2>);
+
+pub struct SavesPlugin;
+impl Plugin for SavesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActiveSlot(1))
+            .init_resource::<PendingCheckpoint>()
+            .add_systems(OnExit(GameState::Loading), apply_pending_checkpoint)
+            .add_systems(Update, quick_save_load)
+            .add_console_command("save", "save <slot> - save the game", save_command)
+            .add_console_command("load", "load <slot> - load a saved game", load_command)
+            .add_console_command("slots", "slots - list save slots", list_slots);
+    }
+}
+
+fn apply_pending_checkpoint(
+    mut pending: ResMut<PendingCheckpoint>,
+    mut active_checkpoint: ResMut<ActiveCheckpoint>,
+) {
+    if let Some(position) = pending.take() {
+        **active_checkpoint = Some(position);
+    }
+}
+
+fn quick_save_load(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    slot: Res<ActiveSlot>,
+) {
+    let slot = **slot;
+    if input.just_pressed(QUICK_SAVE_KEY) && *state.get() == GameState::Playing {
+        commands.queue(move |world: &mut World| match save_slot(world, slot) {
+            Ok(message) => info!("{}", message),
+            Err(e) => error!("{}", e),
+        });
+    } else if input.just_pressed(QUICK_LOAD_KEY) {
+        commands.queue(move |world: &mut World| match load_slot(world, slot) {
+            Ok(message) => info!("{}", message),
+            Err(e) => error!("{}", e),
+        });
+    }
+}
+
+fn save_slot(world: &mut World, slot: usize) -> Result<String, String> {
+    check_slot(slot)?;
+    if *world.resource::<State<GameState>>().get() != GameState::Playing {
+        return Err("can only save while playing".into());
+    }
+
+    SaveData::capture(world)
+        .save_file(slot)
+        .map_err(|e| format!("could not save slot {}: {}", slot, e))?;
+    **world.resource_mut::<ActiveSlot>() = slot;
+    Ok(format!("saved slot {}", slot))
+}
+
+fn load_slot(world: &mut World, slot: usize) -> Result<String, String> {
+    check_slot(slot)?;
+    let data = SaveData::load_file(slot)
+        .map_err(|e| format!("could not load slot {}: {}", slot, e))?
+        .ok_or_else(|| format!("slot {} is empty", slot))?;
+
+    data.restore(world);
+    **world.resource_mut::<ActiveSlot>() = slot;
+    Ok(format!("loaded slot {}", slot))
+}
+
+fn check_slot(slot: usize) -> Result<(), String> {
+    if (1..=SLOT_COUNT).contains(&slot) {
+        Ok(())
+    } else {
+        Err(format!("slots are 1 to {}", SLOT_COUNT))
+    }
+}
+
+fn save_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    save_slot(world, parse_arg(args, 0)?)
+}
+
+fn load_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    load_slot(world, parse_arg(args, 0)?)
+}
+
+fn list_slots(_world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let lines: Vec<String> = (1..=SLOT_COUNT)
+        .map(|slot| match SaveData::load_file(slot) {
+            Ok(Some(data)) => format!(
+                "{}: level {}, score {}, {} lives",
+                slot,
+                data.level + 1,
+                data.score,
+                data.lives
+            ),
+            Ok(None) => format!("{}: empty", slot),
+            Err(e) => format!("{}: unreadable ({})", slot, e),
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SaveData {
+        SaveData {
+            level: 1,
+            checkpoint: Some((350., -150.)),
+            score: 1234,
+            lives: 2,
+            unlocked_levels: 2,
+            levels_cleared: 1,
+            play_time: 95.5,
+        }
+    }
+
+    #[test]
+    fn round_trips_through_the_header() {
+        let text = sample().encode().unwrap();
+        assert!(text.starts_with(&format!("{} {} ", MAGIC, SAVE_VERSION)));
+        assert_eq!(SaveData::decode(&text, &MIGRATIONS).unwrap(), sample());
+    }
+
+    #[test]
+    fn rejects_a_corrupted_body() {
+        let text = sample().encode().unwrap().replace("1234", "9999");
+        let error = SaveData::decode(&text, &MIGRATIONS).unwrap_err();
+        assert!(error.to_string().contains("checksum"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(SaveData::decode("hello\n()", &MIGRATIONS).is_err());
+        assert!(SaveData::decode("", &MIGRATIONS).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let body = ron::ser::to_string(&sample()).unwrap();
+        for version in [0, SAVE_VERSION + 1] {
+            let error = SaveData::decode(&with_header(version, &body), &MIGRATIONS).unwrap_err();
+            assert!(error.to_string().contains("version"), "{}", error);
+        }
+    }
+
+    /// Pretend version 1 called the score `points`
+    fn rename_points(body: &str) -> Result<String, Box<dyn Error>> {
+        if !body.contains("points:") {
+            return Err("not a version 1 body".into());
+        }
+        Ok(body.replace("points:", "score:"))
+    }
+
+    /// Pretend version 2 had no play time
+    fn add_play_time(body: &str) -> Result<String, Box<dyn Error>> {
+        Ok(body.replacen('(', "(play_time: 95.5, ", 1))
+    }
+
+    const FAKE_MIGRATIONS: [Migration; 2] = [rename_points, add_play_time];
+
+    #[test]
+    fn migrates_old_versions_step_by_step() {
+        let v2 = "(level: 1, checkpoint: Some((350.0, -150.0)), score: 1234, \
+                  lives: 2, unlocked_levels: 2, levels_cleared: 1)";
+        let v1 = v2.replace("score:", "points:");
+
+        assert_eq!(
+            SaveData::decode(&with_header(1, &v1), &FAKE_MIGRATIONS).unwrap(),
+            sample()
+        );
+        // Only the steps after its own version run
+        assert_eq!(
+            SaveData::decode(&with_header(2, v2), &FAKE_MIGRATIONS).unwrap(),
+            sample()
+        );
+        // Already current, so no steps at all
+        let v3 = ron::ser::to_string(&sample()).unwrap();
+        assert_eq!(
+            SaveData::decode(&with_header(3, &v3), &FAKE_MIGRATIONS).unwrap(),
+            sample()
+        );
+        assert!(SaveData::decode(&with_header(4, &v3), &FAKE_MIGRATIONS).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+use bevy::{
+    prelude::*,
+    window::{MonitorSelection, PresentMode, PrimaryWindow, VideoModeSelection, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::PathBuf};
+
+use crate::{TITLE, WIN_H, WIN_W};
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 3] = [
+        DisplayMode::Windowed,
+        DisplayMode::Borderless,
+        DisplayMode::Fullscreen,
+    ];
+
+    fn window_mode(self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+            DisplayMode::Fullscreen => {
+                WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current)
+            }
+        }
+    }
+}
+
+/// The `PresentMode`s worth offering, in a form that can be saved
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Vsync {
+    #[default]
+    On,
+    Off,
+    /// Vsync where supported, without the extra latency
+    Fast,
+}
+
+impl Vsync {
+    pub const ALL: [Vsync; 3] = [Vsync::On, Vsync::Off, Vsync::Fast];
+
+    fn present_mode(self) -> PresentMode {
+        match self {
+            Vsync::On => PresentMode::AutoVsync,
+            Vsync::Off => PresentMode::AutoNoVsync,
+            Vsync::Fast => PresentMode::Mailbox,
+        }
+    }
+}
+
+/// Multiples of the virtual resolution offered for the windowed size
+pub const WINDOW_SCALES: [f32; 5] = [0.5, 0.75, 1., 1.5, 2.];
+
+/// Player options, saved to `settings.ron` in the user's config folder
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub display_mode: DisplayMode,
+    pub vsync: Vsync,
+    /// Size of the window when windowed, as a multiple of `WIN_W` x `WIN_H`
+    pub window_scale: f32,
+    /// Volumes from 0 to 1; music and sound effects are both scaled by master
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    /// Record playtest sessions, see `telemetry.rs`
+    pub telemetry: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            display_mode: DisplayMode::default(),
+            vsync: Vsync::default(),
+            window_scale: 1.,
+            master_volume: 1.,
+            music_volume: 1.,
+            sfx_volume: 1.,
+            telemetry: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings file, falling back to defaults if it is missing or broken
+    ///
+    /// This runs before logging is set up, so rather than warning about a
+    /// broken file it hands back the problem for the caller to log later.
+    pub fn load() -> (Self, Option<String>) {
+        let path = settings_path();
+        match fs::read_to_string(&path) {
+            Ok(text) => match ron::de::from_str::<Settings>(&text) {
+                Ok(settings) => (settings.validated(), None),
+                Err(e) => (
+                    Settings::default(),
+                    Some(format!("Ignoring unreadable {}: {}", path.display(), e)),
+                ),
+            },
+            Err(_) => (Settings::default(), None),
+        }
+    }
+
+    /// Pull hand-edited values back to ones the options menu could have set
+    fn validated(mut self) -> Self {
+        self.window_scale = if self.window_scale.is_finite() {
+            WINDOW_SCALES
+                .into_iter()
+                .min_by(|a, b| {
+                    (a - self.window_scale)
+                        .abs()
+                        .total_cmp(&(b - self.window_scale).abs())
+                })
+                .unwrap_or(1.)
+        } else {
+            1.
+        };
+        for volume in [
+            &mut self.master_volume,
+            &mut self.music_volume,
+            &mut self.sfx_volume,
+        ] {
+            *volume = if volume.is_finite() {
+                volume.clamp(0., 1.)
+            } else {
+                1.
+            };
+        }
+        self
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        let path = settings_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// The primary window as these settings describe it
+    pub fn window(&self) -> Window {
+        let mut window = Window {
+            title: String::from(TITLE),
+            ..default()
+        };
+        self.apply(&mut window);
+        window
+    }
+
+    /// Volume for a sound effect, once both its channels are applied
+    pub fn sfx(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+
+    fn same_window(&self, other: &Settings) -> bool {
+        self.display_mode == other.display_mode
+            && self.vsync == other.vsync
+            && self.window_scale == other.window_scale
+    }
+
+    fn apply(&self, window: &mut Window) {
+        window.mode = self.display_mode.window_mode();
+        window.present_mode = self.vsync.present_mode();
+        if self.display_mode == DisplayMode::Windowed {
+            window
+                .resolution
+                .set(WIN_W * self.window_scale, WIN_H * self.window_scale);
+        }
+    }
+}
+
+/// Settings live in the OS config folder, or next to the game if there isn't one
+fn settings_path() -> PathBuf {
+    dirs::config_dir()
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+        .unwrap_or_default()
+        .join(SETTINGS_FILE)
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // Normally already loaded by main() to build the window
+        if !app.world().contains_resource::<Settings>() {
+            let (settings, problem) = Settings::load();
+            if let Some(problem) = problem {
+                warn!("{}", problem);
+            }
+            app.insert_resource(settings);
+        }
+        app.add_systems(Update, apply_settings.run_if(resource_changed::<Settings>));
+    }
+}
+
+/// Push changed settings to the window and save them for next time
+fn apply_settings(
+    settings: Res<Settings>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut applied: Local<Option<Settings>>,
+) {
+    // The window was already created from these
+    if settings.is_added() {
+        *applied = Some(settings.clone());
+        return;
+    }
+
+    // Only touch the window for window settings, so changing the volume
+    // doesn't undo the player resizing it by hand
+    if applied
+        .as_ref()
+        .is_none_or(|applied| !settings.same_window(applied))
+    {
+        settings.apply(&mut window);
+    }
+    *applied = Some(settings.clone());
+
+    match settings.save() {
+        Ok(()) => info!("Saved settings to {}", settings_path().display()),
+        Err(e) => error!("Could not save settings: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validated_snaps_window_scale() {
+        let scale = |window_scale| {
+            Settings {
+                window_scale,
+                ..default()
+            }
+            .validated()
+            .window_scale
+        };
+        assert_eq!(scale(1.5), 1.5);
+        assert_eq!(scale(1.6), 1.5);
+        assert_eq!(scale(100.), 2.);
+        assert_eq!(scale(-3.), 0.5);
+        assert_eq!(scale(f32::NAN), 1.);
+    }
+
+    #[test]
+    fn validated_clamps_volumes() {
+        let settings = Settings {
+            master_volume: 4.,
+            music_volume: -1.,
+            sfx_volume: f32::INFINITY,
+            ..default()
+        }
+        .validated();
+        assert_eq!(settings.master_volume, 1.);
+        assert_eq!(settings.music_volume, 0.);
+        assert_eq!(settings.sfx_volume, 1.);
+    }
+}
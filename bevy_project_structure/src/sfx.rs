@@ -0,0 +1,244 @@
+use bevy::{audio::Volume, prelude::*};
+use rand::{Rng, seq::IndexedRandom};
+use std::collections::HashMap;
+
+use crate::{
+    collectible::Collected,
+    enemy::EnemyStepped,
+    player::{PlayerAnimationFrame, PlayerDied, PlayerJumped, PlayerLanded},
+    settings::Settings,
+    spatial_audio::{SpatialEmitter, SpatialFalloff},
+    viewport::VirtualViewport,
+    win::Win,
+};
+
+/// Walking frames where a foot hits the ground
+const FOOTSTEP_FRAMES: [usize; 2] = [0, 2];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sfx {
+    Jump,
+    Land,
+    Pickup,
+    Damage,
+    Win,
+    Footstep,
+    EnemyStep,
+}
+
+impl Sfx {
+    const ALL: [Sfx; 7] = [
+        Sfx::Jump,
+        Sfx::Land,
+        Sfx::Pickup,
+        Sfx::Damage,
+        Sfx::Win,
+        Sfx::Footstep,
+        Sfx::EnemyStep,
+    ];
+
+    fn def(self) -> SoundDef {
+        match self {
+            Sfx::Jump => SoundDef {
+                variants: &["sfx/jump1.wav", "sfx/jump2.wav", "sfx/jump3.wav"],
+                volume: 0.6,
+                pitch_jitter: 0.08,
+                max_instances: 2,
+            },
+            Sfx::Land => SoundDef {
+                variants: &["sfx/land1.wav", "sfx/land2.wav"],
+                volume: 0.8,
+                pitch_jitter: 0.1,
+                max_instances: 2,
+            },
+            Sfx::Pickup => SoundDef {
+                variants: &["sfx/pickup1.wav", "sfx/pickup2.wav", "sfx/pickup3.wav"],
+                volume: 0.6,
+                pitch_jitter: 0.05,
+                max_instances: 4,
+            },
+            Sfx::Damage => SoundDef {
+                variants: &["sfx/hurt1.wav", "sfx/hurt2.wav"],
+                volume: 0.7,
+                pitch_jitter: 0.1,
+                max_instances: 1,
+            },
+            Sfx::Win => SoundDef {
+                variants: &["sfx/win.wav"],
+                volume: 0.8,
+                pitch_jitter: 0.,
+                max_instances: 1,
+            },
+            Sfx::Footstep => SoundDef {
+                variants: &["sfx/step1.wav", "sfx/step2.wav", "sfx/step3.wav"],
+                volume: 0.3,
+                pitch_jitter: 0.15,
+                max_instances: 2,
+            },
+            Sfx::EnemyStep => SoundDef {
+                variants: &["sfx/enemy_step1.wav", "sfx/enemy_step2.wav"],
+                volume: 0.5,
+                pitch_jitter: 0.1,
+                max_instances: 3,
+            },
+        }
+    }
+}
+
+struct SoundDef {
+    /// Files picked from at random each time the sound plays
+    variants: &'static [&'static str],
+    volume: f32,
+    /// Playback speed varies by up to this much either way
+    pitch_jitter: f32,
+    /// Further requests are dropped while this many are already playing
+    max_instances: usize,
+}
+
+/// Ask for a sound effect to be played
+#[derive(Event)]
+pub struct PlaySfx {
+    pub sfx: Sfx,
+    /// Where in the world the sound comes from, or `None` to play it evenly
+    /// in both ears at full volume
+    pub position: Option<Vec2>,
+}
+
+impl PlaySfx {
+    pub fn new(sfx: Sfx) -> Self {
+        Self {
+            sfx,
+            position: None,
+        }
+    }
+
+    pub fn at(sfx: Sfx, position: Vec2) -> Self {
+        Self {
+            sfx,
+            position: Some(position),
+        }
+    }
+}
+
+/// Every variant of every sound, loaded once up front
+#[derive(Resource, Deref)]
+struct SfxPool(HashMap<Sfx, Vec<Handle<AudioSource>>>);
+
+/// A playing sound effect, despawned when it finishes
+#[derive(Component)]
+struct SfxInstance(Sfx);
+
+pub struct SfxPlugin;
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySfx>()
+            .add_systems(Startup, load_sfx)
+            // Not gated on Playing: events are only sent while playing, but
+            // winning or losing the last life leaves Playing the next frame,
+            // before a gated reader could hear about it
+            .add_systems(Update, (gameplay_sfx, play_sfx).chain());
+    }
+}
+
+fn load_sfx(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let pool = Sfx::ALL
+        .into_iter()
+        .map(|sfx| {
+            let handles = sfx
+                .def()
+                .variants
+                .iter()
+                .map(|path| asset_server.load(*path))
+                .collect();
+            (sfx, handles)
+        })
+        .collect();
+    commands.insert_resource(SfxPool(pool));
+}
+
+/// Turn what happens in the game into sounds
+fn gameplay_sfx(
+    mut jumped: EventReader<PlayerJumped>,
+    mut landed: EventReader<PlayerLanded>,
+    mut collected: EventReader<Collected>,
+    mut died: EventReader<PlayerDied>,
+    mut win: EventReader<Win>,
+    mut frames: EventReader<PlayerAnimationFrame>,
+    mut enemy_steps: EventReader<EnemyStepped>,
+    mut play: EventWriter<PlaySfx>,
+) {
+    // The player is always on screen, so their sounds don't need a position
+    play.write_batch(jumped.read().map(|_| PlaySfx::new(Sfx::Jump)));
+    play.write_batch(landed.read().map(|_| PlaySfx::new(Sfx::Land)));
+    play.write_batch(died.read().map(|_| PlaySfx::new(Sfx::Damage)));
+    play.write_batch(win.read().map(|_| PlaySfx::new(Sfx::Win)));
+    play.write_batch(
+        frames
+            .read()
+            .filter(|event| FOOTSTEP_FRAMES.contains(&event.frame))
+            .map(|_| PlaySfx::new(Sfx::Footstep)),
+    );
+
+    play.write_batch(
+        collected
+            .read()
+            .map(|event| PlaySfx::at(Sfx::Pickup, event.position)),
+    );
+    play.write_batch(
+        enemy_steps
+            .read()
+            .map(|event| PlaySfx::at(Sfx::EnemyStep, event.position)),
+    );
+}
+
+fn play_sfx(
+    mut commands: Commands,
+    mut requests: EventReader<PlaySfx>,
+    pool: Res<SfxPool>,
+    settings: Res<Settings>,
+    falloff: Res<SpatialFalloff>,
+    viewport: Res<VirtualViewport>,
+    camera: Single<&GlobalTransform, With<Camera>>,
+    playing: Query<&SfxInstance>,
+) {
+    let listener = camera.translation().truncate();
+    let mut rng = rand::rng();
+    let mut counts: HashMap<Sfx, usize> = HashMap::new();
+    for instance in &playing {
+        *counts.entry(instance.0).or_default() += 1;
+    }
+
+    for PlaySfx { sfx, position } in requests.read() {
+        let def = sfx.def();
+        let gain = position.map_or(1., |position| falloff.gain(position, listener, &viewport));
+        if gain <= 0. {
+            // Too far away to hear, so don't take up an instance
+            continue;
+        }
+
+        let count = counts.entry(*sfx).or_default();
+        if *count >= def.max_instances {
+            continue;
+        }
+        let Some(handle) = pool.get(sfx).and_then(|handles| handles.choose(&mut rng)) else {
+            continue;
+        };
+        *count += 1;
+
+        let speed = 1. + rng.random_range(-1.0..=1.0) * def.pitch_jitter;
+        let mut sound = commands.spawn((
+            AudioPlayer::new(handle.clone()),
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::Linear(def.volume * settings.sfx() * gain))
+                .with_speed(speed)
+                .with_spatial(position.is_some()),
+            SfxInstance(*sfx),
+        ));
+        if let Some(position) = position {
+            sound.insert((
+                Transform::from_translation(position.extend(0.)),
+                SpatialEmitter { volume: def.volume },
+            ));
+        }
+    }
+}
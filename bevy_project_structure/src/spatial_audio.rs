@@ -0,0 +1,117 @@
+use bevy::{
+    audio::{DefaultSpatialScale, SpatialScale, Volume},
+    prelude::*,
+};
+
+use crate::{
+    TILE_SIZE,
+    console::{ConsoleExt, parse_arg},
+    settings::Settings,
+    viewport::VirtualViewport,
+};
+
+/// World units are shrunk this much before reaching the audio backend, which
+/// keeps its own inverse square falloff from kicking in; `SpatialFalloff`
+/// does that job instead
+const AUDIO_SCALE: f32 = 1. / 4000.;
+
+/// How spatial sounds fade with distance from the middle of the view
+#[derive(Resource)]
+pub struct SpatialFalloff {
+    /// Closer than this, sounds play at full volume
+    pub full_volume_distance: f32,
+    /// Further than this, sounds are silent
+    pub silent_distance: f32,
+    /// Sounds fade out over this far past the edge of the view, so nothing
+    /// off screen is ever heard
+    pub offscreen_margin: f32,
+}
+
+impl Default for SpatialFalloff {
+    fn default() -> Self {
+        Self {
+            full_volume_distance: 300.,
+            silent_distance: 900.,
+            offscreen_margin: TILE_SIZE,
+        }
+    }
+}
+
+impl SpatialFalloff {
+    /// Volume multiplier for a sound at `emitter` heard from a camera at `listener`
+    pub fn gain(&self, emitter: Vec2, listener: Vec2, viewport: &VirtualViewport) -> f32 {
+        let distance = emitter.distance(listener);
+        let range = (self.silent_distance - self.full_volume_distance).max(f32::EPSILON);
+        let falloff = 1. - ((distance - self.full_volume_distance) / range).clamp(0., 1.);
+
+        // How far outside the view the sound is, on whichever axis is worse
+        let outside = ((emitter - listener).abs() - viewport.max()).max_element();
+        let onscreen = 1. - (outside / self.offscreen_margin.max(f32::EPSILON)).clamp(0., 1.);
+
+        falloff * onscreen
+    }
+}
+
+/// A sound effect played at a point in the world
+#[derive(Component)]
+pub struct SpatialEmitter {
+    /// Volume before falloff and the options menu are applied
+    pub volume: f32,
+}
+
+pub struct SpatialAudioPlugin;
+impl Plugin for SpatialAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DefaultSpatialScale(SpatialScale::new_2d(AUDIO_SCALE)))
+            .init_resource::<SpatialFalloff>()
+            .add_systems(Update, attenuate_emitters)
+            .add_console_command(
+                "set falloff",
+                "set falloff <full> <silent> - distances spatial sounds fade between",
+                set_falloff,
+            );
+    }
+}
+
+/// Ears for the camera, far enough apart that sounds at the edges of the
+/// view are panned all the way
+pub fn listener(viewport: &VirtualViewport) -> SpatialListener {
+    SpatialListener::new(viewport.width() / 2.)
+}
+
+/// Keep emitters' volume in step with the camera as it follows the player
+fn attenuate_emitters(
+    camera: Single<&GlobalTransform, With<Camera>>,
+    falloff: Res<SpatialFalloff>,
+    viewport: Res<VirtualViewport>,
+    settings: Res<Settings>,
+    mut emitters: Query<(&GlobalTransform, &SpatialEmitter, &mut SpatialAudioSink)>,
+) {
+    let listener = camera.translation().truncate();
+    for (transform, emitter, mut sink) in emitters.iter_mut() {
+        let gain = falloff.gain(transform.translation().truncate(), listener, &viewport);
+        sink.set_volume(Volume::Linear(emitter.volume * settings.sfx() * gain));
+    }
+}
+
+fn set_falloff(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let full: f32 = parse_arg(args, 0)?;
+    let silent: f32 = parse_arg(args, 1)?;
+    if let Some(bad) = [full, silent]
+        .into_iter()
+        .find(|d| !d.is_finite() || *d < 0.)
+    {
+        return Err(format!("distances must be 0 or more, not {}", bad));
+    }
+    if silent < full {
+        return Err("silent distance must be at least the full volume distance".into());
+    }
+
+    let mut falloff = world.resource_mut::<SpatialFalloff>();
+    falloff.full_volume_distance = full;
+    falloff.silent_distance = silent;
+    Ok(format!(
+        "full volume within {}, silent past {}",
+        full, silent
+    ))
+}
@@ -0,0 +1,397 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, fs, path::PathBuf};
+
+use crate::{
+    GameState,
+    campaign::CurrentLevel,
+    checkpoint::{ActiveCheckpoint, CheckpointReached},
+    level::LevelHandle,
+    level_data::LevelData,
+    loading::despawn_with,
+    trigger::run_trigger_actions,
+    win::{Win, win_event_listener},
+};
+
+const BESTS_FILE: &str = "personal_bests.ron";
+const AHEAD_COLOR: Color = Color::srgb(0.3, 0.9, 0.4);
+const BEHIND_COLOR: Color = Color::srgb(0.95, 0.35, 0.3);
+
+/// Times of the level being played, in game seconds
+///
+/// A split is taken at each checkpoint the first time it is reached and at the
+/// goal, so the last split of a finished run is its final time.
+#[derive(Resource, Default)]
+pub struct Speedrun {
+    pub level: String,
+    pub time: f32,
+    pub splits: Vec<Split>,
+    pub running: bool,
+    /// Runs that start from a checkpoint or go through the editor can't set a
+    /// personal best
+    pub ranked: bool,
+    /// Sum of the final times of every level cleared since the first one
+    pub campaign_time: f32,
+}
+
+/// Time a run got somewhere
+///
+/// Splits are matched up by where they were taken rather than their order, so
+/// a run that skips a checkpoint is still compared like for like.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Split {
+    /// Checkpoint the split was taken at, or `None` for the goal
+    pub at: Option<(f32, f32)>,
+    pub time: f32,
+}
+
+impl Speedrun {
+    /// Difference from the personal best at the latest split, negative when
+    /// ahead. Nothing if the best never reached the same place.
+    pub fn delta(&self, best: Option<&Vec<Split>>) -> Option<f32> {
+        let latest = self.splits.last()?;
+        let matching = best?.iter().find(|split| split.at == latest.at)?;
+        Some(latest.time - matching.time)
+    }
+
+    fn split(&mut self, at: Option<(f32, f32)>) {
+        let time = self.time;
+        self.splits.push(Split { at, time });
+    }
+}
+
+/// Sent when a finished run beats the personal best of its level
+#[derive(Event)]
+pub struct NewPersonalBest;
+
+/// Splits of the fastest finished run of each level, by level name
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct PersonalBests(HashMap<String, Vec<Split>>);
+
+impl PersonalBests {
+    fn load() -> Self {
+        let path = bests_path();
+        match fs::read_to_string(&path) {
+            Ok(text) => ron::de::from_str(&text).map(Self).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let text = ron::ser::to_string_pretty(&self.0, ron::ser::PrettyConfig::default())?;
+        let path = bests_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Final time of the best run of `level`
+    pub fn final_time(&self, level: &str) -> Option<f32> {
+        let splits = self.get(level)?;
+        splits
+            .iter()
+            .find(|split| split.at.is_none())
+            .map(|split| split.time)
+    }
+}
+
+/// Bests are kept in the OS data folder, next to the saves
+fn bests_path() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+        .unwrap_or_default()
+        .join(BESTS_FILE)
+}
+
+/// Minutes, seconds and hundredths
+pub fn format_time(seconds: f32) -> String {
+    let hundredths = (seconds.max(0.) * 100.).round() as u32;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
+
+fn format_delta(delta: f32) -> String {
+    let sign = if delta < 0. { '-' } else { '+' };
+    format!("{}{:.2}", sign, delta.abs())
+}
+
+#[derive(Component)]
+struct SpeedrunHud;
+
+#[derive(Component)]
+struct SpeedrunDelta;
+
+#[derive(Component)]
+struct FinalTime;
+
+pub struct SpeedrunPlugin;
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Speedrun>()
+            .insert_resource(PersonalBests::load())
+            .add_event::<NewPersonalBest>()
+            .add_systems(Startup, setup_hud)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::Loading,
+                    entered: GameState::Playing,
+                },
+                start_run,
+            )
+            .add_systems(OnEnter(GameState::Editor), unrank_run)
+            .add_systems(OnEnter(GameState::Playing), show_hud::<true>)
+            .add_systems(OnExit(GameState::Playing), show_hud::<false>)
+            // The goal can be reached on the frame the level ends, so these
+            // read events whatever the state. They also have to see the goal
+            // the frame it's reached, or the Win screen would come up first.
+            .add_systems(
+                Update,
+                (
+                    tick_run.run_if(in_state(GameState::Playing)),
+                    split_at_checkpoints,
+                    finish_run,
+                    update_hud.run_if(in_state(GameState::Playing)),
+                )
+                    .chain()
+                    .after(run_trigger_actions)
+                    .before(win_event_listener),
+            )
+            .add_systems(OnEnter(GameState::Win), show_final_time)
+            .add_systems(OnExit(GameState::Win), despawn_with::<FinalTime>);
+    }
+}
+
+/// Hidden until a level is being played
+fn setup_hud(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::End,
+                ..default()
+            },
+            Visibility::Hidden,
+            SpeedrunHud,
+        ))
+        .with_children(|hud| {
+            hud.spawn((
+                Text::new(format_time(0.)),
+                TextFont {
+                    font_size: 28.,
+                    ..default()
+                },
+            ));
+            hud.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 18.,
+                    ..default()
+                },
+                SpeedrunDelta,
+            ));
+        });
+}
+
+fn show_hud<const SHOW: bool>(mut hud: Single<&mut Visibility, With<SpeedrunHud>>) {
+    **hud = if SHOW {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+}
+
+pub fn start_run(
+    current_level: Res<CurrentLevel>,
+    level_handle: Res<LevelHandle>,
+    levels: Res<Assets<LevelData>>,
+    active_checkpoint: Res<ActiveCheckpoint>,
+    mut run: ResMut<Speedrun>,
+) {
+    let campaign_time = if **current_level == 0 {
+        0.
+    } else {
+        run.campaign_time
+    };
+    *run = Speedrun {
+        level: levels.get(&**level_handle).map_or_else(
+            || format!("#{}", **current_level + 1),
+            |level| level.name.clone(),
+        ),
+        running: true,
+        // A loaded save puts the player at a checkpoint partway through
+        ranked: active_checkpoint.is_none(),
+        campaign_time,
+        ..default()
+    };
+}
+
+fn unrank_run(mut run: ResMut<Speedrun>) {
+    run.ranked = false;
+}
+
+fn tick_run(time: Res<Time>, mut run: ResMut<Speedrun>) {
+    if run.running {
+        run.time += time.delta_secs();
+    }
+}
+
+fn split_at_checkpoints(mut reached: EventReader<CheckpointReached>, mut run: ResMut<Speedrun>) {
+    for CheckpointReached { position } in reached.read() {
+        let at = Some((position.x, position.y));
+        // Touching a checkpoint again doesn't count
+        if run.running && !run.splits.iter().any(|split| split.at == at) {
+            run.split(at);
+        }
+    }
+}
+
+pub fn finish_run(
+    mut win: EventReader<Win>,
+    mut run: ResMut<Speedrun>,
+    mut bests: ResMut<PersonalBests>,
+    mut new_best: EventWriter<NewPersonalBest>,
+) {
+    if win.is_empty() {
+        return;
+    }
+    win.clear();
+    if !run.running {
+        return;
+    }
+
+    let time = run.time;
+    run.split(None);
+    run.running = false;
+    run.campaign_time += time;
+    info!("Finished {} in {}", run.level, format_time(time));
+
+    if !run.ranked {
+        return;
+    }
+    if bests.final_time(&run.level).is_none_or(|best| time < best) {
+        info!("New personal best");
+        let splits = run.splits.clone();
+        bests.insert(run.level.clone(), splits);
+        new_best.write(NewPersonalBest);
+        if let Err(e) = bests.save() {
+            error!("Could not save personal bests: {}", e);
+        }
+    }
+}
+
+fn update_hud(
+    run: Res<Speedrun>,
+    bests: Res<PersonalBests>,
+    hud: Single<&Children, With<SpeedrunHud>>,
+    mut time_text: Query<&mut Text, Without<SpeedrunDelta>>,
+    mut delta_text: Single<(&mut Text, &mut TextColor), With<SpeedrunDelta>>,
+) {
+    if let Some(&child) = hud.first()
+        && let Ok(mut text) = time_text.get_mut(child)
+    {
+        **text = format_time(run.time);
+    }
+
+    let (text, color) = &mut *delta_text;
+    match run.delta(bests.get(&run.level)) {
+        Some(delta) => {
+            ***text = format_delta(delta);
+            ***color = if delta <= 0. {
+                AHEAD_COLOR
+            } else {
+                BEHIND_COLOR
+            };
+        }
+        None => {
+            let best = bests.final_time(&run.level);
+            ***text = best.map_or_else(String::new, |best| format!("PB {}", format_time(best)));
+            ***color = Color::WHITE;
+        }
+    }
+}
+
+fn show_final_time(mut commands: Commands, run: Res<Speedrun>, bests: Res<PersonalBests>) {
+    let mut lines = format!("Final time {}", format_time(run.time));
+    if let Some(best) = bests.final_time(&run.level) {
+        if run.ranked && best == run.time {
+            lines.push_str("  New personal best!");
+        } else {
+            lines.push_str(&format!(
+                "  ({} against {})",
+                format_delta(run.time - best),
+                format_time(best)
+            ));
+        }
+    }
+    lines.push_str(&format!("\nTotal {}", format_time(run.campaign_time)));
+
+    commands.spawn((
+        Text::new(lines),
+        TextFont {
+            font_size: 28.,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.),
+            width: Val::Percent(100.),
+            ..default()
+        },
+        FinalTime,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(at: Option<(f32, f32)>, time: f32) -> Split {
+        Split { at, time }
+    }
+
+    #[test]
+    fn deltas_compare_the_same_checkpoint() {
+        let first = Some((1050., -150.));
+        let second = Some((3850., -150.));
+        let best = vec![split(first, 10.), split(second, 30.), split(None, 45.)];
+
+        let mut run = Speedrun::default();
+        assert_eq!(run.delta(Some(&best)), None);
+
+        run.splits = vec![split(first, 12.)];
+        assert_eq!(run.delta(Some(&best)), Some(2.));
+
+        // Skipping the second checkpoint still compares the goal with the goal
+        run.splits.push(split(None, 40.));
+        assert_eq!(run.delta(Some(&best)), Some(-5.));
+
+        // A checkpoint the best never touched has nothing to compare with
+        run.splits = vec![split(Some((500., 50.)), 5.)];
+        assert_eq!(run.delta(Some(&best)), None);
+        assert_eq!(run.delta(None), None);
+    }
+
+    #[test]
+    fn final_time_is_the_goal_split() {
+        let mut bests = PersonalBests::default();
+        bests.insert(
+            String::from("First Steps"),
+            vec![split(Some((1050., -150.)), 10.), split(None, 45.)],
+        );
+        assert_eq!(bests.final_time("First Steps"), Some(45.));
+        assert_eq!(bests.final_time("Nowhere"), None);
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{app::AppExit, prelude::*, state::state::StateTransitionEvent};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    GameState,
+    campaign::CurrentLevel,
+    collectible::Collected,
+    console::ConsoleExt,
+    level::LevelHandle,
+    level_data::LevelData,
+    player::{Player, PlayerDied},
+    settings::Settings,
+    viewport::VirtualViewport,
+    win::Win,
+};
+
+/// Seconds between recorded player positions
+const POSITION_INTERVAL: f32 = 0.5;
+/// Seconds between writes to disk
+const FLUSH_INTERVAL: f32 = 5.;
+
+/// One line of a session file
+///
+/// Positions are in world units measured from the bottom left corner of the
+/// level, so they line up with its tiles whatever the window size.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Record {
+    /// Seconds since the session started
+    pub t: f64,
+    #[serde(flatten)]
+    pub event: TelemetryEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    SessionStart { unix_time: u64 },
+    StateChange { state: String },
+    Death { level: String, x: f32, y: f32 },
+    Pickup { level: String, x: f32, y: f32 },
+    LevelComplete { level: String, time: f32 },
+    Position { level: String, x: f32, y: f32 },
+}
+
+/// Session file being written, while telemetry is enabled
+#[derive(Resource)]
+struct TelemetrySession {
+    writer: BufWriter<File>,
+    started: f64,
+}
+
+/// The level being played and how long it has taken so far
+#[derive(Resource, Default)]
+struct LevelClock {
+    level: String,
+    time: f32,
+}
+
+#[derive(Resource, Deref, DerefMut)]
+struct PositionTimer(Timer);
+
+#[derive(Resource, Deref, DerefMut)]
+struct FlushTimer(Timer);
+
+pub struct TelemetryPlugin;
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelClock>()
+            .insert_resource(PositionTimer(Timer::from_seconds(
+                POSITION_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .insert_resource(FlushTimer(Timer::from_seconds(
+                FLUSH_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .add_systems(Update, open_or_close_session)
+            // Nothing else runs, or even reads events, unless a session is open
+            .add_systems(
+                OnExit(GameState::Loading),
+                start_level_clock.run_if(resource_exists::<TelemetrySession>),
+            )
+            .add_systems(
+                Update,
+                (
+                    record_state_changes,
+                    (tick_level_clock, record_positions).run_if(in_state(GameState::Playing)),
+                    // Events from the last frame of a level arrive after it ends
+                    (record_deaths, record_pickups, record_wins),
+                    flush_session,
+                )
+                    .chain()
+                    .after(open_or_close_session)
+                    .run_if(resource_exists::<TelemetrySession>),
+            )
+            .add_systems(
+                Last,
+                close_on_exit
+                    .run_if(on_event::<AppExit>)
+                    .run_if(resource_exists::<TelemetrySession>),
+            )
+            .add_console_command(
+                "telemetry",
+                "telemetry <on|off> - record playtest sessions",
+                set_telemetry,
+            );
+    }
+}
+
+/// Sessions go in the OS data folder, or next to the game if there isn't one
+fn session_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+        .unwrap_or_default()
+        .join("telemetry")
+}
+
+/// A new file for a session started at `unix_time`, never one that exists
+///
+/// Sessions started within the same second get `_2`, `_3` and so on.
+fn create_session_file(dir: &Path, unix_time: u64) -> std::io::Result<(File, PathBuf)> {
+    for attempt in 1.. {
+        let name = match attempt {
+            1 => format!("session_{}.jsonl", unix_time),
+            n => format!("session_{}_{}.jsonl", unix_time, n),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+impl TelemetrySession {
+    fn open(now: f64) -> std::io::Result<(Self, PathBuf)> {
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let dir = session_dir();
+        fs::create_dir_all(&dir)?;
+        let (file, path) = create_session_file(&dir, unix_time)?;
+
+        let mut session = Self {
+            writer: BufWriter::new(file),
+            started: now,
+        };
+        session.record(now, TelemetryEvent::SessionStart { unix_time });
+        Ok((session, path))
+    }
+
+    fn record(&mut self, now: f64, event: TelemetryEvent) {
+        let record = Record {
+            t: now - self.started,
+            event,
+        };
+        // A lost line isn't worth interrupting a playtest over
+        if let Err(e) = serde_json::to_writer(&mut self.writer, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"))
+        {
+            warn!("Could not write telemetry: {}", e);
+        }
+    }
+}
+
+fn open_or_close_session(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    session: Option<ResMut<TelemetrySession>>,
+    mut failed: Local<bool>,
+) {
+    match (settings.telemetry, session) {
+        (true, None) if !*failed => match TelemetrySession::open(time.elapsed_secs_f64()) {
+            Ok((session, path)) => {
+                info!("Recording telemetry to {}", path.display());
+                commands.insert_resource(session);
+            }
+            Err(e) => {
+                // Don't try again every frame
+                error!("Could not start telemetry: {}", e);
+                *failed = true;
+            }
+        },
+        (false, Some(mut session)) => {
+            let _ = session.writer.flush();
+            commands.remove_resource::<TelemetrySession>();
+            info!("Stopped recording telemetry");
+        }
+        _ => {}
+    }
+}
+
+fn start_level_clock(
+    current_level: Res<CurrentLevel>,
+    level_handle: Res<LevelHandle>,
+    levels: Res<Assets<LevelData>>,
+    mut clock: ResMut<LevelClock>,
+) {
+    clock.level = levels.get(&**level_handle).map_or_else(
+        || format!("#{}", **current_level + 1),
+        |level| level.name.clone(),
+    );
+    clock.time = 0.;
+}
+
+fn tick_level_clock(time: Res<Time>, mut clock: ResMut<LevelClock>) {
+    clock.time += time.delta_secs();
+}
+
+fn record_state_changes(
+    time: Res<Time<Real>>,
+    mut transitions: EventReader<StateTransitionEvent<GameState>>,
+    mut session: ResMut<TelemetrySession>,
+) {
+    for transition in transitions.read() {
+        if let Some(state) = transition.entered {
+            session.record(
+                time.elapsed_secs_f64(),
+                TelemetryEvent::StateChange {
+                    state: format!("{:?}", state),
+                },
+            );
+        }
+    }
+}
+
+fn record_deaths(
+    time: Res<Time<Real>>,
+    viewport: Res<VirtualViewport>,
+    clock: Res<LevelClock>,
+    mut died: EventReader<PlayerDied>,
+    mut session: ResMut<TelemetrySession>,
+) {
+    for event in died.read() {
+        let position = event.position - viewport.min();
+        session.record(
+            time.elapsed_secs_f64(),
+            TelemetryEvent::Death {
+                level: clock.level.clone(),
+                x: position.x,
+                y: position.y,
+            },
+        );
+    }
+}
+
+fn record_pickups(
+    time: Res<Time<Real>>,
+    viewport: Res<VirtualViewport>,
+    clock: Res<LevelClock>,
+    mut collected: EventReader<Collected>,
+    mut session: ResMut<TelemetrySession>,
+) {
+    for event in collected.read() {
+        let position = event.position - viewport.min();
+        session.record(
+            time.elapsed_secs_f64(),
+            TelemetryEvent::Pickup {
+                level: clock.level.clone(),
+                x: position.x,
+                y: position.y,
+            },
+        );
+    }
+}
+
+fn record_wins(
+    time: Res<Time<Real>>,
+    clock: Res<LevelClock>,
+    mut win: EventReader<Win>,
+    mut session: ResMut<TelemetrySession>,
+) {
+    if win.is_empty() {
+        return;
+    }
+    win.clear();
+
+    session.record(
+        time.elapsed_secs_f64(),
+        TelemetryEvent::LevelComplete {
+            level: clock.level.clone(),
+            time: clock.time,
+        },
+    );
+}
+
+fn record_positions(
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    viewport: Res<VirtualViewport>,
+    clock: Res<LevelClock>,
+    mut timer: ResMut<PositionTimer>,
+    player: Single<&Transform, With<Player>>,
+    mut session: ResMut<TelemetrySession>,
+) {
+    // Game time, so pausing doesn't pile up samples in one spot
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let position = player.translation.truncate() - viewport.min();
+    session.record(
+        real_time.elapsed_secs_f64(),
+        TelemetryEvent::Position {
+            level: clock.level.clone(),
+            x: position.x,
+            y: position.y,
+        },
+    );
+}
+
+fn flush_session(
+    time: Res<Time<Real>>,
+    mut timer: ResMut<FlushTimer>,
+    mut session: ResMut<TelemetrySession>,
+) {
+    if timer.tick(time.delta()).just_finished()
+        && let Err(e) = session.writer.flush()
+    {
+        warn!("Could not write telemetry: {}", e);
+    }
+}
+
+fn close_on_exit(mut session: ResMut<TelemetrySession>) {
+    let _ = session.writer.flush();
+}
+
+fn set_telemetry(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let enabled = match args.first().copied() {
+        Some("on") => true,
+        Some("off") => false,
+        _ => return Err(String::from("telemetry on or telemetry off?")),
+    };
+    world.resource_mut::<Settings>().telemetry = enabled;
+    Ok(format!("telemetry {}", if enabled { "on" } else { "off" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_in_the_same_second_get_their_own_file() {
+        let dir = std::env::temp_dir().join(format!("telemetry_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let (_, first) = create_session_file(&dir, 1666).unwrap();
+        let (_, second) = create_session_file(&dir, 1666).unwrap();
+        assert_eq!(first.file_name().unwrap(), "session_1666.jsonl");
+        assert_eq!(second.file_name().unwrap(), "session_1666_2.jsonl");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::{math::bounding::Aabb2d, prelude::*};
+
+use crate::{
+    GameState, TILE_SIZE,
+    checkpoint::{ActiveCheckpoint, CheckpointReached},
+    collision::{Collider, overlaps},
+    level_data::{TriggerAction, TriggerData, TriggerShape},
+    loading::despawn_with,
+    music::MusicOverride,
+    player::{Player, PlayerDied},
+    viewport::VirtualViewport,
+    win::Win,
+};
+
+const GOAL_COLOR: Color = Color::srgba(1., 0.85, 0.1, 0.25);
+
+/// A zone that reports the player entering, staying in and leaving it, and
+/// carries out its action
+#[derive(Component)]
+pub struct Trigger {
+    /// In world units, centered on the entity
+    pub shape: TriggerShape,
+    pub action: TriggerAction,
+    /// Whether the player was inside last frame
+    occupied: bool,
+}
+
+impl Trigger {
+    fn touches(&self, center: Vec2, player: &Aabb2d) -> bool {
+        match self.shape {
+            TriggerShape::Box(width, height) => {
+                overlaps(&Aabb2d::new(center, Vec2::new(width, height) / 2.), player)
+            }
+            TriggerShape::Radius(radius) => player.closest_point(center).distance(center) < radius,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct TriggerEnter(pub Entity);
+
+/// Sent every frame the player spends inside a trigger after entering it
+#[derive(Event)]
+pub struct TriggerStay(pub Entity);
+
+#[derive(Event)]
+pub struct TriggerExit(pub Entity);
+
+/// Where `move_camera` holds the camera instead of following the player
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct CameraLock(Option<f32>);
+
+pub struct TriggerPlugin;
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TriggerEnter>()
+            .add_event::<TriggerStay>()
+            .add_event::<TriggerExit>()
+            .init_resource::<CameraLock>()
+            .add_systems(
+                Update,
+                (detect_triggers, run_trigger_actions)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            // Triggers come back unoccupied, so a lock held now would never see its exit
+            .add_systems(
+                OnExit(GameState::Playing),
+                (despawn_with::<Trigger>, unlock_camera),
+            );
+    }
+}
+
+pub fn spawn_trigger(commands: &mut Commands, data: &TriggerData, viewport: &VirtualViewport) {
+    let shape = data.shape.scaled(TILE_SIZE);
+    let mut trigger = commands.spawn((
+        Transform::from_translation(data.position(viewport).extend(1.5)),
+        Visibility::default(),
+        Trigger {
+            shape,
+            action: data.action.clone(),
+            occupied: false,
+        },
+    ));
+
+    // The only trigger the player needs to see
+    if data.action == TriggerAction::Goal {
+        let size = match shape {
+            TriggerShape::Box(width, height) => Vec2::new(width, height),
+            TriggerShape::Radius(radius) => Vec2::splat(radius * 2.),
+        };
+        trigger.insert(Sprite::from_color(GOAL_COLOR, size));
+    }
+}
+
+/// Outline a trigger's shape, for the editor and debug overlay
+pub fn draw_trigger_shape(gizmos: &mut Gizmos, center: Vec2, shape: TriggerShape, color: Color) {
+    match shape {
+        TriggerShape::Box(width, height) => {
+            gizmos.rect_2d(center, Vec2::new(width, height), color);
+        }
+        TriggerShape::Radius(radius) => {
+            gizmos.circle_2d(center, radius, color);
+        }
+    }
+}
+
+fn unlock_camera(mut camera_lock: ResMut<CameraLock>) {
+    **camera_lock = None;
+}
+
+fn detect_triggers(
+    player: Single<(&Transform, &Collider), With<Player>>,
+    mut triggers: Query<(Entity, &Transform, &mut Trigger)>,
+    mut entered: EventWriter<TriggerEnter>,
+    mut stayed: EventWriter<TriggerStay>,
+    mut exited: EventWriter<TriggerExit>,
+) {
+    let (player_transform, player_collider) = player.into_inner();
+    let player_box = player_collider.bounds(player_transform);
+
+    for (entity, transform, mut trigger) in triggers.iter_mut() {
+        let inside = trigger.touches(transform.translation.truncate(), &player_box);
+        match (trigger.occupied, inside) {
+            (false, true) => {
+                entered.write(TriggerEnter(entity));
+            }
+            (true, true) => {
+                stayed.write(TriggerStay(entity));
+            }
+            (true, false) => {
+                exited.write(TriggerExit(entity));
+            }
+            (false, false) => {}
+        }
+        trigger.occupied = inside;
+    }
+}
+
+pub fn run_trigger_actions(
+    mut entered: EventReader<TriggerEnter>,
+    mut stayed: EventReader<TriggerStay>,
+    mut exited: EventReader<TriggerExit>,
+    triggers: Query<(&Transform, &Trigger)>,
+    player: Single<&Transform, With<Player>>,
+    mut active_checkpoint: ResMut<ActiveCheckpoint>,
+    mut music_override: ResMut<MusicOverride>,
+    mut camera_lock: ResMut<CameraLock>,
+    mut win: EventWriter<Win>,
+    mut reached: EventWriter<CheckpointReached>,
+    mut died: EventWriter<PlayerDied>,
+) {
+    for TriggerEnter(entity) in entered.read() {
+        let Ok((transform, trigger)) = triggers.get(*entity) else {
+            continue;
+        };
+        let center = transform.translation.truncate();
+
+        match &trigger.action {
+            TriggerAction::Goal => {
+                win.write(Win);
+            }
+            TriggerAction::Checkpoint => {
+                **active_checkpoint = Some(center);
+                reached.write(CheckpointReached { position: center });
+            }
+            TriggerAction::Hazard => {
+                died.write(PlayerDied {
+                    position: player.translation.truncate(),
+                });
+            }
+            TriggerAction::Music(track) => **music_override = Some(track.clone()),
+            TriggerAction::CameraLock => **camera_lock = Some(center.x),
+        }
+    }
+
+    for TriggerExit(entity) in exited.read() {
+        if let Ok((_, trigger)) = triggers.get(*entity)
+            && trigger.action == TriggerAction::CameraLock
+        {
+            **camera_lock = None;
+        }
+    }
+
+    // Leaving one of two overlapping locks shouldn't free the camera
+    for TriggerStay(entity) in stayed.read() {
+        if let Ok((transform, trigger)) = triggers.get(*entity)
+            && trigger.action == TriggerAction::CameraLock
+            && camera_lock.is_none()
+        {
+            **camera_lock = Some(transform.translation.x);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::{
+    prelude::*,
+    render::camera::{ScalingMode, Viewport},
+    window::PrimaryWindow,
+};
+
+use crate::{
+    WIN_H, WIN_W,
+    console::{ConsoleExt, parse_arg},
+};
+
+/// The area of the world the camera shows, whatever size the window is
+///
+/// Layout should be worked out from this rather than the window, so the game
+/// looks the same at any resolution.
+#[derive(Resource, Clone, Copy)]
+pub struct VirtualViewport {
+    pub size: Vec2,
+}
+
+impl Default for VirtualViewport {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(WIN_W, WIN_H),
+        }
+    }
+}
+
+impl VirtualViewport {
+    pub fn width(&self) -> f32 {
+        self.size.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.size.y
+    }
+
+    /// Bottom left corner of the view while the camera is at the origin
+    pub fn min(&self) -> Vec2 {
+        -self.size / 2.
+    }
+
+    /// Top right corner of the view while the camera is at the origin
+    pub fn max(&self) -> Vec2 {
+        self.size / 2.
+    }
+}
+
+/// How the virtual viewport is fit into a window of a different size
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// As large as fits, with bars on two sides
+    #[default]
+    Letterbox,
+    /// The largest whole number multiple that fits, for crisp pixels
+    Integer,
+}
+
+impl std::str::FromStr for ScaleMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "letterbox" => Ok(ScaleMode::Letterbox),
+            "integer" => Ok(ScaleMode::Integer),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct ViewportPlugin;
+impl Plugin for ViewportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VirtualViewport>()
+            .init_resource::<ScaleMode>()
+            .add_systems(PostUpdate, fit_camera)
+            .add_console_command(
+                "scale",
+                "scale <letterbox|integer> - how the game fits the window",
+                set_scale_mode,
+            );
+    }
+}
+
+/// Projection that always shows exactly the virtual viewport
+pub fn virtual_projection(viewport: &VirtualViewport) -> Projection {
+    Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::Fixed {
+            width: viewport.width(),
+            height: viewport.height(),
+        },
+        ..OrthographicProjection::default_2d()
+    })
+}
+
+/// Resize the camera's viewport whenever the window or scale mode changes
+fn fit_camera(
+    window: Single<Ref<Window>, With<PrimaryWindow>>,
+    virtual_viewport: Res<VirtualViewport>,
+    scale_mode: Res<ScaleMode>,
+    mut camera: Single<&mut Camera>,
+) {
+    if !window.is_changed() && !scale_mode.is_changed() && !virtual_viewport.is_changed() {
+        return;
+    }
+
+    let window_size = window.physical_size().as_vec2();
+    if window_size.min_element() <= 0. {
+        // Minimized
+        return;
+    }
+
+    let fit = (window_size / virtual_viewport.size).min_element();
+    let scale = match *scale_mode {
+        // Fall back to letterboxing if the window is smaller than one multiple
+        ScaleMode::Integer if fit >= 1. => fit.floor(),
+        _ => fit,
+    };
+
+    let size = (virtual_viewport.size * scale).round().min(window_size);
+    camera.viewport = Some(Viewport {
+        physical_position: ((window_size - size) / 2.).as_uvec2(),
+        physical_size: size.as_uvec2(),
+        ..default()
+    });
+}
+
+fn set_scale_mode(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let mode: ScaleMode = parse_arg(args, 0)?;
+    *world.resource_mut::<ScaleMode>() = mode;
+    Ok(format!("scale mode = {:?}", mode))
+}
@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 
-use crate::{GameState, loading::LoadingAssets};
+use crate::{
+    GameState,
+    campaign::{CurrentLevel, Progress},
+    loading::LoadingAssets,
+};
 
 #[derive(Event, Default)]
 pub struct Win;
@@ -16,7 +20,10 @@ impl Plugin for WinPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, load_win)
             .add_systems(OnEnter(GameState::Win), setup_win)
-            .add_systems(Update, win_event_listener)
+            .add_systems(
+                Update,
+                win_event_listener.run_if(in_state(GameState::Playing)),
+            )
             .add_event::<Win>();
     }
 }
@@ -46,12 +53,30 @@ fn setup_win(
     camera.translation.x = 0.;
 }
 
-fn win_event_listener(
+pub fn win_event_listener(
     mut win_event: EventReader<Win>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut progress: ResMut<Progress>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if !win_event.is_empty() {
+    if win_event.is_empty() {
+        return;
+    }
+    win_event.clear();
+
+    progress.levels_cleared += 1;
+    info!(
+        "Cleared level {} ({:.1}s played)",
+        **current_level + 1,
+        progress.play_time
+    );
+
+    if current_level.is_last() {
         next_state.set(GameState::Win);
-        win_event.clear();
+    } else {
+        // Load the next level through the loading screen
+        **current_level += 1;
+        progress.unlocked_levels = progress.unlocked_levels.max(**current_level + 1);
+        next_state.set(GameState::Loading);
     }
 }